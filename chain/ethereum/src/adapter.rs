@@ -1209,6 +1209,7 @@ mod tests {
     use std::collections::{HashMap, HashSet};
     use std::iter::FromIterator;
     use std::str::FromStr;
+    use std::sync::Arc;
 
     #[test]
     fn ethereum_log_filter_codec() {
@@ -1485,7 +1486,7 @@ mod tests {
     fn matching_ethereum_call_filter() {
         let call = |to: Address, input: Vec<u8>| EthereumCall {
             to,
-            input: bytes(input),
+            input: Arc::new(bytes(input)),
             ..Default::default()
         };
 
@@ -7,12 +7,13 @@ use graph::{
     blockchain::{
         self, Block as BlockchainBlock, BlockPtr, BlockTime, ChainStoreBlock, ChainStoreData,
     }, components::ethereum::types::{EthereumBlock, StoreTransactionReceipt}, prelude::{
-        web3::{self, types::{Bytes, H160, H2048, H256, H64, U256, U64}},
+        web3::{self, types::{AccessList, AccessListItem, Bytes, H160, H2048, H256, H64, U256, U64}},
         BlockNumber, Error, EthereumBlockWithCalls, EthereumCall,
         LightEthereumBlock,
     }
 };
 use graph::components::ethereum::types::LightTransaction;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::{convert::TryFrom, fmt::Debug};
 
@@ -47,11 +48,24 @@ pub struct CallAt<'a> {
     call: &'a Call,
     block: &'a Block,
     trace: &'a TransactionTrace,
+    positions: &'a CallPositions,
 }
 
 impl<'a> CallAt<'a> {
-    pub fn new(call: &'a Call, block: &'a Block, trace: &'a TransactionTrace) -> Self {
-        Self { call, block, trace }
+    /// `positions` must have been built with `call_positions` from the calls
+    /// of `trace`
+    pub fn new(
+        call: &'a Call,
+        block: &'a Block,
+        trace: &'a TransactionTrace,
+        positions: &'a CallPositions,
+    ) -> Self {
+        Self {
+            call,
+            block,
+            trace,
+            positions,
+        }
     }
 }
 
@@ -68,16 +82,76 @@ impl<'a> TryInto<EthereumCall> for CallAt<'a> {
                 .as_ref()
                 .map_or_else(|| U256::from(0), |v| v.into()),
             gas_used: U256::from(self.call.gas_consumed),
-            input: Bytes(self.call.input.clone()),
-            output: Bytes(self.call.return_data.clone()),
+            input: Arc::new(Bytes(self.call.input.clone())),
+            output: Arc::new(Bytes(self.call.return_data.clone())),
             block_hash: self.block.hash.try_decode_proto("call block hash")?,
             block_number: self.block.number as i32,
             transaction_hash: Some(self.trace.hash.try_decode_proto("call transaction hash")?),
             transaction_index: self.trace.index as u64,
+            call_type: CallType::try_from(self.call.call_type)
+                .map(Into::into)
+                .unwrap_or_default(),
+            depth: self.call.depth as usize,
+            trace_address: trace_address(self.call, self.positions),
         })
     }
 }
 
+/// The index of the parent of each call of a transaction and the position
+/// of the call among its siblings, keyed by the index of the call. Root
+/// calls are not included
+pub type CallPositions = HashMap<u32, (u32, usize)>;
+
+/// Builds the `CallPositions` for the calls of one transaction
+pub fn call_positions(calls: &[Call]) -> CallPositions {
+    let mut calls: Vec<_> = calls.iter().filter(|call| call.depth > 0).collect();
+    calls.sort_by_key(|call| call.index);
+
+    let mut siblings: HashMap<u32, usize> = HashMap::new();
+    calls
+        .into_iter()
+        .map(|call| {
+            let position = siblings.entry(call.parent_index).or_default();
+            let entry = (call.index, (call.parent_index, *position));
+            *position += 1;
+            entry
+        })
+        .collect()
+}
+
+/// Reconstructs the Parity-style trace address of `call`, i.e., the position
+/// of each call among its siblings on the path from the root call to `call`
+fn trace_address(call: &Call, positions: &CallPositions) -> Vec<usize> {
+    let mut address = Vec::with_capacity(call.depth as usize);
+    let mut index = call.index;
+    // Bounded so that malformed parent indexes can not make this loop forever
+    while address.len() < positions.len() {
+        match positions.get(&index) {
+            Some((parent_index, position)) => {
+                address.push(*position);
+                index = *parent_index;
+            }
+            None => break,
+        }
+    }
+    address.reverse();
+    address
+}
+
+impl From<CallType> for graph::components::ethereum::types::CallType {
+    fn from(call_type: CallType) -> Self {
+        use graph::components::ethereum::types::CallType as EthereumCallType;
+
+        match call_type {
+            CallType::Call => EthereumCallType::Call,
+            CallType::Callcode => EthereumCallType::CallCode,
+            CallType::Delegate => EthereumCallType::DelegateCall,
+            CallType::Static => EthereumCallType::StaticCall,
+            CallType::Unspecified | CallType::Create => EthereumCallType::None,
+        }
+    }
+}
+
 impl TryInto<web3::types::Call> for Call {
     type Error = Error;
 
@@ -200,7 +274,7 @@ impl<'a> TryInto<web3::types::Transaction> for TransactionTraceAt<'a> {
             r: None,
             s: None,
             raw: None,
-            access_list: None,
+            access_list: get_access_list(self.trace)?,
             max_fee_per_gas: None,
             max_priority_fee_per_gas: None,
             transaction_type: None,
@@ -226,6 +300,19 @@ impl<'a> TryInto<LightTransaction> for TransactionTraceAt<'a> {
             gas_price: self.trace.gas_price.as_ref().map(|x| x.into()),
             gas: U256::from(self.trace.gas_limit),
             input: Bytes::from(self.trace.input.clone()),
+            v: signature_bytes(&self.trace.v, 8).map(U64::from_big_endian),
+            r: signature_bytes(&self.trace.r, 32).map(U256::from_big_endian),
+            s: signature_bytes(&self.trace.s, 32).map(U256::from_big_endian),
+            transaction_type: Some(U64::from(self.trace.r#type as u64)),
+            max_fee_per_gas: self.trace.max_fee_per_gas.as_ref().map(|x| x.into()),
+            max_priority_fee_per_gas: self
+                .trace
+                .max_priority_fee_per_gas
+                .as_ref()
+                .map(|x| x.into()),
+            access_list: get_access_list(self.trace)?,
+            max_fee_per_blob_gas: None,
+            blob_versioned_hashes: None,
         })
     }
 }
@@ -364,7 +451,7 @@ impl TryInto<EthereumBlockWithCalls> for &Block {
                                     .try_decode_proto("transaction logs bloom")?,
                                 from: t.from.try_decode_proto("transaction from")?,
                                 to: get_to_address(t)?,
-                                transaction_type: None,
+                                transaction_type: Some(U64::from(t.r#type as u64)),
                                 effective_gas_price: None,
                             })
                         })
@@ -382,11 +469,12 @@ impl TryInto<EthereumBlockWithCalls> for &Block {
                 self.transaction_traces
                     .iter()
                     .flat_map(|trace| {
+                        let positions = call_positions(&trace.calls);
                         trace
                             .calls
                             .iter()
                             .filter(|call| !call.status_reverted && !call.status_failed)
-                            .map(|call| CallAt::new(call, self, trace).try_into())
+                            .map(|call| CallAt::new(call, self, trace, &positions).try_into())
                             .collect::<Vec<Result<EthereumCall, Error>>>()
                     })
                     .collect::<Result<_, _>>()?,
@@ -527,7 +615,138 @@ mod test {
 
     use crate::codec::BlockHeader;
 
-    use super::Block;
+    use super::{
+        call_positions, get_access_list, signature_bytes, trace_address, transaction_trace,
+        AccessTuple, Block, Call, TransactionReceipt, TransactionTrace, TransactionTraceStatus,
+    };
+    use graph::prelude::EthereumBlockWithCalls;
+    use graph::prelude::web3::types::{AccessListItem, H160, H256};
+
+    #[test]
+    fn signature_bytes_never_overflow() {
+        assert_eq!(None, signature_bytes(&[], 8));
+        assert_eq!(Some(&[0x25][..]), signature_bytes(&[0x25], 8));
+        // Leading zeros do not count towards the length
+        assert_eq!(Some(&[0x01, 0x25][..]), signature_bytes(&[0, 0, 0x01, 0x25], 2));
+        assert_eq!(Some(&[][..]), signature_bytes(&[0, 0], 8));
+        // A `v` for a chain id that does not fit into 64 bits
+        assert_eq!(None, signature_bytes(&[0x01; 9], 8));
+        assert_eq!(None, signature_bytes(&[0xff; 33], 32));
+    }
+
+    #[test]
+    fn access_list_from_trace() {
+        let mut trace = TransactionTrace {
+            r#type: transaction_trace::Type::TrxTypeAccessList as i32,
+            access_list: vec![
+                AccessTuple {
+                    address: vec![0xaa; 20],
+                    storage_keys: vec![vec![0x01; 32], vec![0x02; 32]],
+                },
+                AccessTuple {
+                    address: vec![0xbb; 20],
+                    storage_keys: vec![],
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(vec![
+                AccessListItem {
+                    address: H160::repeat_byte(0xaa),
+                    storage_keys: vec![H256::repeat_byte(0x01), H256::repeat_byte(0x02)],
+                },
+                AccessListItem {
+                    address: H160::repeat_byte(0xbb),
+                    storage_keys: vec![],
+                },
+            ]),
+            get_access_list(&trace).unwrap()
+        );
+
+        trace.access_list[0].storage_keys.push(vec![0x03; 31]);
+        assert!(get_access_list(&trace).is_err());
+
+        trace.r#type = transaction_trace::Type::TrxTypeLegacy as i32;
+        trace.access_list.clear();
+        assert_eq!(None, get_access_list(&trace).unwrap());
+    }
+
+    #[test]
+    fn trace_address_with_siblings() {
+        let call = |index, parent_index, depth| Call {
+            index,
+            parent_index,
+            depth,
+            ..Default::default()
+        };
+        // 1
+        // ├── 2
+        // │   ├── 4
+        // │   ├── 5
+        // │   │   └── 7
+        // │   └── 6
+        // └── 3
+        let calls = vec![
+            call(1, 0, 0),
+            call(2, 1, 1),
+            call(5, 2, 2),
+            call(4, 2, 2),
+            call(7, 5, 3),
+            call(3, 1, 1),
+            call(6, 2, 2),
+        ];
+        let positions = call_positions(&calls);
+
+        let address = |index| {
+            let call = calls.iter().find(|call| call.index == index).unwrap();
+            trace_address(call, &positions)
+        };
+        assert_eq!(Vec::<usize>::new(), address(1));
+        assert_eq!(vec![0], address(2));
+        assert_eq!(vec![1], address(3));
+        assert_eq!(vec![0, 0], address(4));
+        assert_eq!(vec![0, 1], address(5));
+        assert_eq!(vec![0, 2], address(6));
+        assert_eq!(vec![0, 1, 0], address(7));
+    }
+
+    #[test]
+    fn receipt_keeps_transaction_type() {
+        let block = Block {
+            hash: vec![0x01; 32],
+            number: 1,
+            header: Some(BlockHeader {
+                parent_hash: vec![0; 32],
+                uncle_hash: vec![0; 32],
+                coinbase: vec![0; 20],
+                state_root: vec![0; 32],
+                transactions_root: vec![0; 32],
+                receipt_root: vec![0; 32],
+                mix_hash: vec![0; 32],
+                ..Default::default()
+            }),
+            transaction_traces: vec![TransactionTrace {
+                hash: vec![0x02; 32],
+                from: vec![0x03; 20],
+                to: vec![0x04; 20],
+                // A blob transaction
+                r#type: 3,
+                status: TransactionTraceStatus::Succeeded as i32,
+                receipt: Some(TransactionReceipt {
+                    logs_bloom: vec![0; 256],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let block: EthereumBlockWithCalls = (&block).try_into().unwrap();
+        let receipt = &block.ethereum_block.transaction_receipts[0];
+        assert!(receipt.is_type(3));
+        assert!(!receipt.is_type(2));
+    }
 
     #[test]
     fn ensure_block_serialization() {
@@ -565,3 +784,40 @@ fn get_to_address(trace: &TransactionTrace) -> Result<Option<H160>, Error> {
         Ok(Some(trace.to.try_decode_proto("transaction to address")?))
     }
 }
+
+/// The access list of the transaction. Legacy transactions have none, like
+/// in the JSON-RPC encoding of a transaction
+fn get_access_list(trace: &TransactionTrace) -> Result<Option<AccessList>, Error> {
+    if trace.r#type == transaction_trace::Type::TrxTypeLegacy as i32 {
+        return Ok(None);
+    }
+
+    trace
+        .access_list
+        .iter()
+        .map(|tuple| {
+            Ok(AccessListItem {
+                address: tuple.address.try_decode_proto("access list address")?,
+                storage_keys: tuple
+                    .storage_keys
+                    .iter()
+                    .map(|key| key.try_decode_proto("access list storage key"))
+                    .collect::<Result<Vec<H256>, Error>>()?,
+            })
+        })
+        .collect::<Result<AccessList, Error>>()
+        .map(Some)
+}
+
+/// The significant bytes of a big-endian signature value, if there are at
+/// most `max_len` of them. Empty values and values that do not fit, like a
+/// `v` of an EIP-155 chain with a very large chain id, give `None` so that
+/// decoding them can not panic
+fn signature_bytes(bytes: &[u8], max_len: usize) -> Option<&[u8]> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let significant = &bytes[start..];
+    (significant.len() <= max_len).then_some(significant)
+}
@@ -477,12 +477,9 @@ impl DataSource {
     fn handler_for_call(&self, call: &EthereumCall) -> Result<Option<&MappingCallHandler>, Error> {
         // First four bytes of the input for the call are the first four
         // bytes of hash of the function signature
-        ensure!(
-            call.input.0.len() >= 4,
-            "Ethereum call has input with less than 4 bytes"
-        );
-
-        let target_method_id = &call.input.0[..4];
+        let target_method_id = call
+            .function_selector()
+            .ok_or_else(|| anyhow!("Ethereum call has input with less than 4 bytes"))?;
 
         Ok(self.mapping.call_handlers.iter().find(move |handler| {
             let fhash = keccak256(handler.function.as_bytes());
@@ -1902,10 +1902,12 @@ pub(crate) async fn blocks_with_triggers(
         .load_blocks(logger.cheap_clone(), chain_store.clone(), block_hashes)
         .await?
         .into_iter()
-        .map(
-            move |block| match triggers_by_block.remove(&(block.number() as BlockNumber)) {
+        .map(move |block| {
+            let block = ConfirmedBlock::try_new(block)
+                .ok_or_else(|| anyhow!("loaded a block without a hash or number"))?;
+            match triggers_by_block.remove(&block.number()) {
                 Some(triggers) => Ok(BlockWithTriggers::new(
-                    BlockFinality::Final(block),
+                    BlockFinality::Final(block.into_inner()),
                     triggers,
                     &logger2,
                 )),
@@ -1913,8 +1915,8 @@ pub(crate) async fn blocks_with_triggers(
                     "block {} not found in `triggers_by_block`",
                     block.block_ptr()
                 )),
-            },
-        )
+            }
+        })
         .collect::<Result<_, _>>()?;
 
     // Filter out call triggers that come from unsuccessful transactions
@@ -1971,6 +1973,10 @@ pub(crate) async fn get_calls(
             let calls = if !requires_traces || ethereum_block.transaction_receipts.is_empty() {
                 vec![]
             } else {
+                let block_ptr = ethereum_block
+                    .block
+                    .try_block_ptr()
+                    .ok_or_else(|| anyhow!("cannot get the calls of a pending block"))?;
                 client
                     .rpc()?
                     .cheapest_with(capabilities)
@@ -1978,9 +1984,8 @@ pub(crate) async fn get_calls(
                     .calls_in_block(
                         &logger,
                         subgraph_metrics.clone(),
-                        BlockNumber::try_from(ethereum_block.block.number.unwrap().as_u64())
-                            .unwrap(),
-                        ethereum_block.block.hash.unwrap(),
+                        block_ptr.number,
+                        block_ptr.hash_as_h256(),
                     )
                     .await?
             };
@@ -2042,7 +2047,7 @@ pub(crate) fn parse_call_triggers(
                 move |call| match block.transaction_for_call_succeeded(call) {
                     Ok(true) => Ok(Some(EthereumTrigger::Call(Arc::new(call.clone())))),
                     Ok(false) => Ok(None),
-                    Err(e) => Err(e),
+                    Err(e) => Err(e.into()),
                 },
             )
             .filter_map_ok(|some_trigger| some_trigger)
@@ -2057,12 +2062,16 @@ pub(crate) fn parse_call_triggers(
 pub(crate) fn parse_block_triggers(
     block_filter: &EthereumBlockFilter,
     block: &EthereumBlockWithCalls,
-) -> Vec<EthereumTrigger> {
+) -> anyhow::Result<Vec<EthereumTrigger>> {
     if block_filter.is_empty() {
-        return vec![];
+        return Ok(vec![]);
     }
 
-    let block_ptr = BlockPtr::from(&block.ethereum_block);
+    let block_ptr = block
+        .ethereum_block
+        .block
+        .try_block_ptr()
+        .ok_or_else(|| anyhow!("cannot parse block triggers for a pending block"))?;
     let trigger_every_block = block_filter.trigger_every_block;
     let call_filter = EthereumCallFilter::from(block_filter);
     let block_ptr2 = block_ptr.cheap_clone();
@@ -2127,7 +2136,7 @@ pub(crate) fn parse_block_triggers(
             ));
         }
     }
-    triggers
+    Ok(triggers)
 }
 
 async fn fetch_receipt_from_ethereum_client(
@@ -2740,7 +2749,7 @@ mod tests {
             },
             calls: Some(vec![EthereumCall {
                 to: address(4),
-                input: bytes(vec![1; 36]),
+                input: Arc::new(bytes(vec![1; 36])),
                 ..Default::default()
             }]),
         };
@@ -2760,7 +2769,8 @@ mod tests {
                     trigger_every_block: true,
                 },
                 &block
-            ),
+            )
+            .unwrap(),
             "every block should generate a trigger even when address don't match"
         );
     }
@@ -2906,7 +2916,7 @@ mod tests {
             },
             calls: Some(vec![EthereumCall {
                 to: address(4),
-                input: bytes(vec![1; 36]),
+                input: Arc::new(bytes(vec![1; 36])),
                 ..Default::default()
             }]),
         };
@@ -2920,7 +2930,8 @@ mod tests {
                     trigger_every_block: false,
                 },
                 &block
-            ),
+            )
+            .unwrap(),
             "block filter specifies address 1 but block does not contain any call to it"
         );
     }
@@ -2938,7 +2949,7 @@ mod tests {
             },
             calls: Some(vec![EthereumCall {
                 to: address(4),
-                input: bytes(vec![1; 36]),
+                input: Arc::new(bytes(vec![1; 36])),
                 ..Default::default()
             }]),
         };
@@ -2955,11 +2966,37 @@ mod tests {
                     trigger_every_block: false,
                 },
                 &block
-            ),
+            )
+            .unwrap(),
             "block filter specifies address 4 and block has call to it"
         );
     }
 
+    #[test]
+    fn parse_block_triggers_pending_block() {
+        let block = EthereumBlockWithCalls {
+            ethereum_block: EthereumBlock {
+                block: Arc::new(Block {
+                    hash: None,
+                    number: None,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            calls: Some(vec![]),
+        };
+
+        assert!(parse_block_triggers(
+            &EthereumBlockFilter {
+                polling_intervals: HashSet::new(),
+                contract_addresses: HashSet::new(),
+                trigger_every_block: true,
+            },
+            &block
+        )
+        .is_err());
+    }
+
     fn address(id: u64) -> Address {
         Address::from_low_u64_be(id)
     }
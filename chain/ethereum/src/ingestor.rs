@@ -8,6 +8,7 @@ use graph::util::backoff::ExponentialBackoff;
 use graph::{
     blockchain::{BlockHash, BlockIngestor, BlockPtr, IngestorError},
     cheap_clone::CheapClone,
+    components::ethereum::types::block_ptr_from_hash_block,
     prelude::{
         async_trait, error, ethabi::ethereum_types::H256, info, tokio, trace, warn, ChainStore,
         Error, EthereumBlockWithCalls, LogCode, Logger,
@@ -206,10 +207,9 @@ impl PollingBlockIngestor {
         logger: &Logger,
         eth_adapter: &Arc<EthereumAdapter>,
     ) -> Result<BlockPtr, IngestorError> {
-        eth_adapter
-            .latest_block_header(&logger)
-            .await
-            .map(|block| block.into())
+        // The latest block might be pending, which has no hash or number
+        let block = eth_adapter.latest_block_header(&logger).await?;
+        block_ptr_from_hash_block(&block).map_err(|e| IngestorError::Unknown(e.into()))
     }
 
     async fn eth_adapter(&self) -> anyhow::Result<Arc<EthereumAdapter>> {
@@ -32,7 +32,7 @@ fn test_trigger_ordering() {
 
     let mut call2 = EthereumCall::default();
     call2.transaction_index = 2;
-    call2.input = Bytes(vec![0]);
+    call2.input = Arc::new(Bytes(vec![0]));
     let call2 = EthereumTrigger::Call(Arc::new(call2));
 
     let mut call3 = EthereumCall::default();
@@ -43,7 +43,7 @@ fn test_trigger_ordering() {
     let mut call4 = EthereumCall::default();
     call4.transaction_index = 2;
     // different than call2 so they don't get mistaken as the same
-    call4.input = Bytes(vec![1]);
+    call4.input = Arc::new(Bytes(vec![1]));
     let call4 = EthereumTrigger::Call(Arc::new(call4));
 
     fn create_log(tx_index: u64, log_index: u64) -> Arc<Log> {
@@ -212,3 +212,43 @@ fn test_trigger_dedup() {
 
     assert_eq!(block_with_triggers.trigger_data, expected);
 }
+
+#[test]
+fn test_call_trigger_dedup_ignores_call_tree_position() {
+    let mut call1 = EthereumCall::default();
+    call1.transaction_index = 1;
+    call1.trace_address = vec![0];
+    call1.depth = 1;
+
+    // The same call as `call1`, but recorded at a different position in
+    // the call tree
+    let mut call2 = call1.clone();
+    call2.trace_address = vec![0, 2];
+    call2.depth = 2;
+
+    let mut call3 = EthereumCall::default();
+    call3.transaction_index = 2;
+
+    let call1 = EthereumTrigger::Call(Arc::new(call1));
+    let call2 = EthereumTrigger::Call(Arc::new(call2));
+    let call3 = EthereumTrigger::Call(Arc::new(call3));
+
+    let logger = Logger::root(slog::Discard, o!());
+
+    let mut b: LightEthereumBlock = Default::default();
+    b.number = Some(Default::default());
+    b.hash = Some(Default::default());
+
+    let block_with_triggers = BlockWithTriggers::<crate::Chain>::new(
+        BlockFinality::Final(Arc::new(b)),
+        vec![call3.clone(), call2, call1.clone()],
+        &logger,
+    );
+
+    let expected = vec![call1, call3]
+        .into_iter()
+        .map(|t| Trigger::Chain(t))
+        .collect::<Vec<_>>();
+
+    assert_eq!(block_with_triggers.trigger_data, expected);
+}
@@ -281,7 +281,7 @@ impl PartialEq for EthereumTrigger {
                 a_ptr == b_ptr && a_kind == b_kind
             }
 
-            (Self::Call(a), Self::Call(b)) => a == b,
+            (Self::Call(a), Self::Call(b)) => a.trigger_key() == b.trigger_key(),
 
             (Self::Log(a), Self::Log(b)) => {
                 a.transaction_hash() == b.transaction_hash() && a.log_index() == b.log_index()
@@ -0,0 +1,137 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+
+use clap::Parser;
+use graph::components::ethereum::EthereumCall;
+use graph::prelude::web3::types::{
+    Action, ActionType, Address, Bytes, Call, CallResult, CallType, Res, Trace, H256, U256,
+};
+
+// Use a custom allocator that counts how many allocations the program makes
+// and how many bytes it allocates overall; memory that is freed again is
+// not subtracted
+
+struct Counter;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counter {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ret = System.alloc(layout);
+        if !ret.is_null() {
+            ALLOCATED.fetch_add(layout.size(), SeqCst);
+            ALLOCATIONS.fetch_add(1, SeqCst);
+        }
+        ret
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static A: Counter = Counter;
+
+#[derive(Parser)]
+#[clap(
+    name = "ethereum_calls",
+    about = "Measure allocations for building and cloning the calls of a synthetic block"
+)]
+struct Opt {
+    /// Number of calls in the block
+    #[clap(short, long, default_value = "5000")]
+    calls: usize,
+    /// Number of transactions the calls are spread over
+    #[clap(short, long, default_value = "50")]
+    transactions: usize,
+    /// Size of the input of each call in bytes
+    #[clap(short, long, default_value = "516")]
+    input_size: usize,
+    /// How often all calls are cloned, e.g., once per matching data source
+    #[clap(short = 'n', long, default_value = "3")]
+    clones: usize,
+}
+
+/// Run `f` and return its result with the number of allocations and bytes
+/// it allocated
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize, usize) {
+    let allocations = ALLOCATIONS.load(SeqCst);
+    let allocated = ALLOCATED.load(SeqCst);
+    let res = black_box(f());
+    (
+        res,
+        ALLOCATIONS.load(SeqCst) - allocations,
+        ALLOCATED.load(SeqCst) - allocated,
+    )
+}
+
+fn trace(i: usize, opt: &Opt) -> Trace {
+    let tx = i % opt.transactions;
+    Trace {
+        action: Action::Call(Call {
+            from: Address::from_low_u64_be(tx as u64),
+            to: Address::from_low_u64_be(1000 + (i % 17) as u64),
+            value: U256::zero(),
+            gas: U256::from(100_000),
+            input: Bytes(vec![(i % 251) as u8; opt.input_size]),
+            call_type: CallType::Call,
+        }),
+        result: Some(Res::Call(CallResult {
+            gas_used: U256::from(21_000),
+            output: Bytes(vec![0; 32]),
+        })),
+        trace_address: vec![i / opt.transactions],
+        subtraces: 0,
+        transaction_position: Some(tx),
+        transaction_hash: Some(H256::from_low_u64_be(tx as u64)),
+        block_number: 1,
+        block_hash: H256::from_low_u64_be(1),
+        action_type: ActionType::Call,
+        error: None,
+    }
+}
+
+pub fn main() {
+    let opt = Opt::parse();
+    let traces: Vec<_> = (0..opt.calls).map(|i| trace(i, &opt)).collect();
+
+    let (calls, allocations, bytes) = measure(|| {
+        traces
+            .iter()
+            .filter_map(EthereumCall::try_from_trace)
+            .collect::<Vec<_>>()
+    });
+    println!(
+        "build {} calls from traces: {:>8} allocations {:>12} bytes",
+        calls.len(),
+        allocations,
+        bytes
+    );
+
+    let (clones, allocations, bytes) =
+        measure(|| (0..opt.clones).map(|_| calls.clone()).collect::<Vec<_>>());
+    println!(
+        "clone all calls {}x:          {:>8} allocations {:>12} bytes",
+        opt.clones, allocations, bytes
+    );
+    drop(clones);
+
+    // What cloning cost on top of that while `input` and `output` were
+    // plain `Bytes` that were copied with every clone
+    let mut copies = Vec::with_capacity(opt.clones * calls.len());
+    let ((), allocations, bytes) = measure(|| {
+        for _ in 0..opt.clones {
+            for call in &calls {
+                copies.push((call.input.as_ref().clone(), call.output.as_ref().clone()));
+            }
+        }
+    });
+    println!(
+        "copying input and output too: {:>8} allocations {:>12} bytes more",
+        allocations, bytes
+    );
+    drop(copies);
+}
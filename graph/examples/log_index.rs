@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use graph::components::ethereum::types::StoreTransactionReceipt;
+use graph::components::ethereum::{
+    EthereumBlock, LightEthereumBlock, LightEthereumBlockExt, LightTransaction,
+};
+use graph::prelude::web3::types::{Address, Bytes, Log, H256, U256, U64};
+
+#[derive(Parser)]
+#[clap(
+    name = "log_index",
+    about = "Compare looking up the transactions of logs by scanning with using a LogIndex"
+)]
+struct Opt {
+    /// Number of repetitions of the test
+    #[clap(short, long, default_value = "5")]
+    niter: usize,
+    /// Number of transactions in the block
+    #[clap(short, long, default_value = "2000")]
+    transactions: usize,
+    /// Number of logs in the block
+    #[clap(short, long, default_value = "5000")]
+    logs: usize,
+}
+
+fn block(opt: &Opt) -> EthereumBlock {
+    let tx_hash = |tx: usize| H256::from_low_u64_be(tx as u64 + 1);
+
+    let transactions = (0..opt.transactions)
+        .map(|tx| LightTransaction {
+            hash: tx_hash(tx),
+            transaction_index: Some(U64::from(tx as u64)),
+            ..Default::default()
+        })
+        .collect();
+    let mut receipts: Vec<_> = (0..opt.transactions)
+        .map(|tx| StoreTransactionReceipt {
+            transaction_hash: tx_hash(tx),
+            transaction_index: U64::from(tx as u64),
+            ..Default::default()
+        })
+        .collect();
+    for i in 0..opt.logs {
+        let tx = i % opt.transactions;
+        receipts[tx].logs.push(Log {
+            address: Address::from_low_u64_be((i % 31) as u64),
+            topics: vec![H256::from_low_u64_be(0xe1)],
+            data: Bytes(vec![0; 32]),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: Some(tx_hash(tx)),
+            transaction_index: Some(U64::from(tx as u64)),
+            log_index: Some(U256::from(i as u64)),
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        });
+    }
+
+    EthereumBlock {
+        block: Arc::new(LightEthereumBlock {
+            hash: Some(H256::from_low_u64_be(1)),
+            number: Some(U64::from(1)),
+            transactions,
+            ..Default::default()
+        }),
+        transaction_receipts: receipts.into_iter().map(Arc::new).collect(),
+    }
+}
+
+pub fn main() {
+    let opt = Opt::parse();
+    let block = block(&opt);
+    let logs: Vec<_> = block
+        .transaction_receipts
+        .iter()
+        .flat_map(|receipt| receipt.logs.iter())
+        .collect();
+
+    let mut scan_time = Duration::ZERO;
+    let mut index_time = Duration::ZERO;
+    for _ in 0..opt.niter {
+        let start = Instant::now();
+        let scanned: Vec<_> = logs
+            .iter()
+            .map(|log| block.block.transaction_for_log(log).map(|tx| tx.hash))
+            .collect();
+        scan_time += start.elapsed();
+
+        // Building the index is part of the cost of using it
+        let start = Instant::now();
+        let index = block.build_log_index();
+        let indexed: Vec<_> = logs
+            .iter()
+            .map(|log| index.transaction_for_log(log).map(|tx| tx.hash))
+            .collect();
+        index_time += start.elapsed();
+
+        assert_eq!(scanned, indexed);
+    }
+
+    println!(
+        "{} logs, {} transactions: scan {:?}, index {:?} per iteration",
+        logs.len(),
+        opt.transactions,
+        scan_time / opt.niter as u32,
+        index_time / opt.niter as u32
+    );
+}
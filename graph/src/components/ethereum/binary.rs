@@ -0,0 +1,398 @@
+//! The binary encoding behind `EthereumBlock::to_bytes`.
+//!
+//! bincode is not self-describing, so it can not encode the block types
+//! directly: their serde impls leave out fields that are `None` and write
+//! hashes and numbers as hex strings. The types here mirror them with plain
+//! fields, raw bytes for hashes and addresses, and the words of 256-bit
+//! numbers, which bincode's variable-length integers keep small.
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use web3::types::{AccessListItem, Block, Bytes, Log, H160, H2048, H256, H64, U256, U64};
+
+use super::types::{EthereumBlock, LightEthereumBlock, LightTransaction, StoreTransactionReceipt};
+
+/// The first byte of every encoded block. It needs to be bumped whenever
+/// the layout of the types below changes so that blocks encoded with an
+/// older layout are rejected instead of being misread
+const VERSION: u8 = 1;
+
+type Hash = [u8; 32];
+type Address = [u8; 20];
+type Word = [u64; 4];
+/// A logs bloom; serde only supports arrays of up to 32 elements
+type Bloom = [[u8; 32]; 8];
+
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+pub(super) fn encode(block: &EthereumBlock) -> Result<Vec<u8>, anyhow::Error> {
+    let mut bytes = vec![VERSION];
+    options().serialize_into(&mut bytes, &BinaryEthereumBlock::from(block))?;
+    Ok(bytes)
+}
+
+pub(super) fn decode(bytes: &[u8]) -> Result<EthereumBlock, anyhow::Error> {
+    match bytes.split_first() {
+        Some((&VERSION, rest)) => Ok(options().deserialize::<BinaryEthereumBlock>(rest)?.into()),
+        Some((version, _)) => Err(anyhow!("unknown block encoding version {}", version)),
+        None => Err(anyhow!("encoded block is empty")),
+    }
+}
+
+fn bloom(bloom: &H2048) -> Bloom {
+    let mut words = [[0; 32]; 8];
+    for (word, chunk) in words.iter_mut().zip(bloom.0.chunks(32)) {
+        word.copy_from_slice(chunk);
+    }
+    words
+}
+
+fn from_bloom(words: Bloom) -> H2048 {
+    let mut bloom = H2048::zero();
+    for (chunk, word) in bloom.0.chunks_mut(32).zip(words.iter()) {
+        chunk.copy_from_slice(word);
+    }
+    bloom
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryEthereumBlock {
+    block: BinaryBlock,
+    transaction_receipts: Vec<BinaryReceipt>,
+}
+
+impl From<&EthereumBlock> for BinaryEthereumBlock {
+    fn from(block: &EthereumBlock) -> Self {
+        Self {
+            block: BinaryBlock::from(block.block.as_ref()),
+            transaction_receipts: block
+                .transaction_receipts
+                .iter()
+                .map(|receipt| BinaryReceipt::from(receipt.as_ref()))
+                .collect(),
+        }
+    }
+}
+
+impl From<BinaryEthereumBlock> for EthereumBlock {
+    fn from(block: BinaryEthereumBlock) -> Self {
+        Self {
+            block: Arc::new(block.block.into()),
+            transaction_receipts: block
+                .transaction_receipts
+                .into_iter()
+                .map(|receipt| Arc::new(receipt.into()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryBlock {
+    hash: Option<Hash>,
+    parent_hash: Hash,
+    uncles_hash: Hash,
+    author: Address,
+    state_root: Hash,
+    transactions_root: Hash,
+    receipts_root: Hash,
+    number: Option<u64>,
+    gas_used: Word,
+    gas_limit: Word,
+    base_fee_per_gas: Option<Word>,
+    extra_data: Vec<u8>,
+    logs_bloom: Option<Bloom>,
+    timestamp: Word,
+    difficulty: Word,
+    total_difficulty: Option<Word>,
+    seal_fields: Vec<Vec<u8>>,
+    uncles: Vec<Hash>,
+    transactions: Vec<BinaryTransaction>,
+    size: Option<Word>,
+    mix_hash: Option<Hash>,
+    nonce: Option<[u8; 8]>,
+}
+
+impl From<&LightEthereumBlock> for BinaryBlock {
+    fn from(block: &LightEthereumBlock) -> Self {
+        Self {
+            hash: block.hash.map(|hash| hash.0),
+            parent_hash: block.parent_hash.0,
+            uncles_hash: block.uncles_hash.0,
+            author: block.author.0,
+            state_root: block.state_root.0,
+            transactions_root: block.transactions_root.0,
+            receipts_root: block.receipts_root.0,
+            number: block.number.map(|number| number.as_u64()),
+            gas_used: block.gas_used.0,
+            gas_limit: block.gas_limit.0,
+            base_fee_per_gas: block.base_fee_per_gas.map(|fee| fee.0),
+            extra_data: block.extra_data.0.clone(),
+            logs_bloom: block.logs_bloom.as_ref().map(bloom),
+            timestamp: block.timestamp.0,
+            difficulty: block.difficulty.0,
+            total_difficulty: block.total_difficulty.map(|difficulty| difficulty.0),
+            seal_fields: block
+                .seal_fields
+                .iter()
+                .map(|field| field.0.clone())
+                .collect(),
+            uncles: block.uncles.iter().map(|uncle| uncle.0).collect(),
+            transactions: block
+                .transactions
+                .iter()
+                .map(BinaryTransaction::from)
+                .collect(),
+            size: block.size.map(|size| size.0),
+            mix_hash: block.mix_hash.map(|hash| hash.0),
+            nonce: block.nonce.map(|nonce| nonce.0),
+        }
+    }
+}
+
+impl From<BinaryBlock> for LightEthereumBlock {
+    fn from(block: BinaryBlock) -> Self {
+        Block {
+            hash: block.hash.map(H256),
+            parent_hash: H256(block.parent_hash),
+            uncles_hash: H256(block.uncles_hash),
+            author: H160(block.author),
+            state_root: H256(block.state_root),
+            transactions_root: H256(block.transactions_root),
+            receipts_root: H256(block.receipts_root),
+            number: block.number.map(U64::from),
+            gas_used: U256(block.gas_used),
+            gas_limit: U256(block.gas_limit),
+            base_fee_per_gas: block.base_fee_per_gas.map(U256),
+            extra_data: Bytes(block.extra_data),
+            logs_bloom: block.logs_bloom.map(from_bloom),
+            timestamp: U256(block.timestamp),
+            difficulty: U256(block.difficulty),
+            total_difficulty: block.total_difficulty.map(U256),
+            seal_fields: block.seal_fields.into_iter().map(Bytes).collect(),
+            uncles: block.uncles.into_iter().map(H256).collect(),
+            transactions: block
+                .transactions
+                .into_iter()
+                .map(LightTransaction::from)
+                .collect(),
+            size: block.size.map(U256),
+            mix_hash: block.mix_hash.map(H256),
+            nonce: block.nonce.map(H64),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryTransaction {
+    hash: Hash,
+    nonce: Word,
+    transaction_index: Option<u64>,
+    from: Option<Address>,
+    to: Option<Address>,
+    value: Word,
+    gas_price: Option<Word>,
+    gas: Word,
+    input: Vec<u8>,
+    v: Option<u64>,
+    r: Option<Word>,
+    s: Option<Word>,
+    transaction_type: Option<u64>,
+    max_fee_per_gas: Option<Word>,
+    max_priority_fee_per_gas: Option<Word>,
+    access_list: Option<Vec<(Address, Vec<Hash>)>>,
+    max_fee_per_blob_gas: Option<Word>,
+    blob_versioned_hashes: Option<Vec<Hash>>,
+}
+
+impl From<&LightTransaction> for BinaryTransaction {
+    fn from(tx: &LightTransaction) -> Self {
+        Self {
+            hash: tx.hash.0,
+            nonce: tx.nonce.0,
+            transaction_index: tx.transaction_index.map(|index| index.as_u64()),
+            from: tx.from.map(|from| from.0),
+            to: tx.to.map(|to| to.0),
+            value: tx.value.0,
+            gas_price: tx.gas_price.map(|price| price.0),
+            gas: tx.gas.0,
+            input: tx.input.0.clone(),
+            v: tx.v.map(|v| v.as_u64()),
+            r: tx.r.map(|r| r.0),
+            s: tx.s.map(|s| s.0),
+            transaction_type: tx.transaction_type.map(|t| t.as_u64()),
+            max_fee_per_gas: tx.max_fee_per_gas.map(|fee| fee.0),
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas.map(|fee| fee.0),
+            access_list: tx.access_list.as_ref().map(|items| {
+                items
+                    .iter()
+                    .map(|item| {
+                        (
+                            item.address.0,
+                            item.storage_keys.iter().map(|key| key.0).collect(),
+                        )
+                    })
+                    .collect()
+            }),
+            max_fee_per_blob_gas: tx.max_fee_per_blob_gas.map(|fee| fee.0),
+            blob_versioned_hashes: tx
+                .blob_versioned_hashes
+                .as_ref()
+                .map(|hashes| hashes.iter().map(|hash| hash.0).collect()),
+        }
+    }
+}
+
+impl From<BinaryTransaction> for LightTransaction {
+    fn from(tx: BinaryTransaction) -> Self {
+        Self {
+            hash: H256(tx.hash),
+            nonce: U256(tx.nonce),
+            transaction_index: tx.transaction_index.map(U64::from),
+            from: tx.from.map(H160),
+            to: tx.to.map(H160),
+            value: U256(tx.value),
+            gas_price: tx.gas_price.map(U256),
+            gas: U256(tx.gas),
+            input: Bytes(tx.input),
+            v: tx.v.map(U64::from),
+            r: tx.r.map(U256),
+            s: tx.s.map(U256),
+            transaction_type: tx.transaction_type.map(U64::from),
+            max_fee_per_gas: tx.max_fee_per_gas.map(U256),
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas.map(U256),
+            access_list: tx.access_list.map(|items| {
+                items
+                    .into_iter()
+                    .map(|(address, storage_keys)| AccessListItem {
+                        address: H160(address),
+                        storage_keys: storage_keys.into_iter().map(H256).collect(),
+                    })
+                    .collect()
+            }),
+            max_fee_per_blob_gas: tx.max_fee_per_blob_gas.map(U256),
+            blob_versioned_hashes: tx
+                .blob_versioned_hashes
+                .map(|hashes| hashes.into_iter().map(H256).collect()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryReceipt {
+    transaction_hash: Hash,
+    transaction_index: u64,
+    block_hash: Option<Hash>,
+    block_number: Option<u64>,
+    cumulative_gas_used: Word,
+    gas_used: Option<Word>,
+    contract_address: Option<Address>,
+    logs: Vec<BinaryLog>,
+    status: Option<u64>,
+    root: Option<Hash>,
+    logs_bloom: Bloom,
+    effective_gas_price: Option<Word>,
+    transaction_type: Option<u64>,
+    blob_gas_used: Option<Word>,
+    blob_gas_price: Option<Word>,
+}
+
+impl From<&StoreTransactionReceipt> for BinaryReceipt {
+    fn from(receipt: &StoreTransactionReceipt) -> Self {
+        Self {
+            transaction_hash: receipt.transaction_hash.0,
+            transaction_index: receipt.transaction_index.as_u64(),
+            block_hash: receipt.block_hash.map(|hash| hash.0),
+            block_number: receipt.block_number.map(|number| number.as_u64()),
+            cumulative_gas_used: receipt.cumulative_gas_used.0,
+            gas_used: receipt.gas_used.map(|gas| gas.0),
+            contract_address: receipt.contract_address.map(|address| address.0),
+            logs: receipt.logs.iter().map(BinaryLog::from).collect(),
+            status: receipt.status.map(|status| status.as_u64()),
+            root: receipt.root.map(|root| root.0),
+            logs_bloom: bloom(&receipt.logs_bloom),
+            effective_gas_price: receipt.effective_gas_price.map(|price| price.0),
+            transaction_type: receipt.transaction_type.map(|t| t.as_u64()),
+            blob_gas_used: receipt.blob_gas_used.map(|gas| gas.0),
+            blob_gas_price: receipt.blob_gas_price.map(|price| price.0),
+        }
+    }
+}
+
+impl From<BinaryReceipt> for StoreTransactionReceipt {
+    fn from(receipt: BinaryReceipt) -> Self {
+        Self {
+            transaction_hash: H256(receipt.transaction_hash),
+            transaction_index: U64::from(receipt.transaction_index),
+            block_hash: receipt.block_hash.map(H256),
+            block_number: receipt.block_number.map(U64::from),
+            cumulative_gas_used: U256(receipt.cumulative_gas_used),
+            gas_used: receipt.gas_used.map(U256),
+            contract_address: receipt.contract_address.map(H160),
+            logs: receipt.logs.into_iter().map(Log::from).collect(),
+            status: receipt.status.map(U64::from),
+            root: receipt.root.map(H256),
+            logs_bloom: from_bloom(receipt.logs_bloom),
+            effective_gas_price: receipt.effective_gas_price.map(U256),
+            transaction_type: receipt.transaction_type.map(U64::from),
+            blob_gas_used: receipt.blob_gas_used.map(U256),
+            blob_gas_price: receipt.blob_gas_price.map(U256),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryLog {
+    address: Address,
+    topics: Vec<Hash>,
+    data: Vec<u8>,
+    block_hash: Option<Hash>,
+    block_number: Option<u64>,
+    transaction_hash: Option<Hash>,
+    transaction_index: Option<u64>,
+    log_index: Option<Word>,
+    transaction_log_index: Option<Word>,
+    log_type: Option<String>,
+    removed: Option<bool>,
+}
+
+impl From<&Log> for BinaryLog {
+    fn from(log: &Log) -> Self {
+        Self {
+            address: log.address.0,
+            topics: log.topics.iter().map(|topic| topic.0).collect(),
+            data: log.data.0.clone(),
+            block_hash: log.block_hash.map(|hash| hash.0),
+            block_number: log.block_number.map(|number| number.as_u64()),
+            transaction_hash: log.transaction_hash.map(|hash| hash.0),
+            transaction_index: log.transaction_index.map(|index| index.as_u64()),
+            log_index: log.log_index.map(|index| index.0),
+            transaction_log_index: log.transaction_log_index.map(|index| index.0),
+            log_type: log.log_type.clone(),
+            removed: log.removed,
+        }
+    }
+}
+
+impl From<BinaryLog> for Log {
+    fn from(log: BinaryLog) -> Self {
+        Self {
+            address: H160(log.address),
+            topics: log.topics.into_iter().map(H256).collect(),
+            data: Bytes(log.data),
+            block_hash: log.block_hash.map(H256),
+            block_number: log.block_number.map(U64::from),
+            transaction_hash: log.transaction_hash.map(H256),
+            transaction_index: log.transaction_index.map(U64::from),
+            log_index: log.log_index.map(U256),
+            transaction_log_index: log.transaction_log_index.map(U256),
+            log_type: log.log_type,
+            removed: log.removed,
+        }
+    }
+}
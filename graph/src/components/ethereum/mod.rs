@@ -1,6 +1,9 @@
+mod binary;
 pub mod types;
 
 pub use self::types::{
-    evaluate_transaction_status, EthereumBlock, EthereumBlockV1, EthereumBlockV2, EthereumBlockWithCalls, EthereumCall,
-    LightEthereumBlock, LightEthereumBlockV2, LightEthereumBlockExt, LightTransaction,
+    evaluate_transaction_status, transaction_status, ConfirmedBlock, EthereumBlock,
+    EthereumBlockV1, EthereumBlockV2, EthereumBlockWithCalls, EthereumCall, EthereumTypeError,
+    LightEthereumBlock, LightEthereumBlockExt, LightEthereumBlockV2, LightTransaction,
+    TransactionStatus,
 };
@@ -1,38 +1,312 @@
 use serde::{Deserialize, Serialize};
-use std::{convert::TryFrom, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    sync::Arc,
+};
+use thiserror::Error;
 use web3::types::{
-    Action, Address, Block, Bytes, Index, Log, Res, Trace, Transaction, TransactionReceipt, H2048,
-    H256, U256, U64,
+    AccessList, Action, Address, Block, Bytes, CallRequest, Index, Log, Res, Trace, Transaction,
+    TransactionReceipt, H2048, H256, U256, U64,
 };
 
 use crate::{
     blockchain::{BlockPtr, BlockTime},
-    prelude::{BlockNumber},
+    data::store::scalar::Timestamp,
+    prelude::{BlockNumber, CheapClone},
 };
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LightTransaction {
     /// Hash
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub hash: H256,
     /// Nonce
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub nonce: U256,
     /// Transaction Index. None when pending.
     #[serde(rename = "transactionIndex")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub transaction_index: Option<Index>,
     /// Sender
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub from: Option<Address>,
     /// Recipient (None when contract creation)
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub to: Option<Address>,
     /// Transfered value
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub value: U256,
     /// Gas Price
     #[serde(rename = "gasPrice")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub gas_price: Option<U256>,
     /// Gas amount
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub gas: U256,
     /// Input data
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub input: Bytes,
+    /// ECDSA recovery id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub v: Option<U64>,
+    /// ECDSA signature r
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub r: Option<U256>,
+    /// ECDSA signature s
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub s: Option<U256>,
+    /// Transaction type (EIP-2718). `None` for legacy transactions from nodes
+    /// that predate typed transactions.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub transaction_type: Option<U64>,
+    /// Maximum total fee per gas the sender is willing to pay (EIP-1559)
+    #[serde(
+        rename = "maxFeePerGas",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub max_fee_per_gas: Option<U256>,
+    /// Maximum priority fee per gas the sender is willing to pay (EIP-1559)
+    #[serde(
+        rename = "maxPriorityFeePerGas",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// Access list (EIP-2930)
+    #[serde(
+        rename = "accessList",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<Vec<serde_json::Value>>"))]
+    pub access_list: Option<AccessList>,
+    /// Maximum fee per blob gas the sender is willing to pay (EIP-4844)
+    #[serde(
+        rename = "maxFeePerBlobGas",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub max_fee_per_blob_gas: Option<U256>,
+    /// Versioned hashes of the blobs carried by this transaction (EIP-4844)
+    #[serde(
+        rename = "blobVersionedHashes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<Vec<String>>"))]
+    pub blob_versioned_hashes: Option<Vec<H256>>,
+}
+
+impl LightTransaction {
+    /// Returns `true` if this is a legacy (pre EIP-2718) transaction, i.e.
+    /// the transaction type is either absent or `0`.
+    pub fn is_legacy(&self) -> bool {
+        self.transaction_type.map_or(true, |t| t.is_zero())
+    }
+
+    /// Returns `true` if this transaction deploys a contract
+    pub fn is_contract_creation(&self) -> bool {
+        self.to.is_none()
+    }
+
+    /// Returns `true` if this is an EIP-4844 blob transaction
+    pub fn is_blob_tx(&self) -> bool {
+        self.transaction_type == Some(U64::from(3)) || self.blob_versioned_hashes.is_some()
+    }
+
+    /// The gas price actually paid by this transaction in a block with the
+    /// given `base_fee`. For EIP-1559 transactions this is
+    /// `min(max_fee_per_gas, base_fee + max_priority_fee_per_gas)`; for
+    /// legacy transactions it is the `gas_price`.
+    pub fn effective_gas_price(&self, base_fee: U256) -> U256 {
+        match (self.max_fee_per_gas, self.max_priority_fee_per_gas) {
+            (Some(max_fee), Some(max_priority_fee)) => {
+                max_fee.min(base_fee.saturating_add(max_priority_fee))
+            }
+            _ => self.gas_price.unwrap_or_default(),
+        }
+    }
+
+    /// The tip per gas that the block producer receives from this
+    /// transaction in a block with the given `base_fee`. For EIP-1559
+    /// transactions this is `min(max_priority_fee_per_gas, max_fee_per_gas -
+    /// base_fee)`, for legacy transactions `gas_price - base_fee`; both
+    /// saturate at zero. `None` if the transaction has no fee information
+    pub fn priority_fee_paid(&self, base_fee: U256) -> Option<U256> {
+        match (self.max_fee_per_gas, self.max_priority_fee_per_gas) {
+            (Some(max_fee), Some(max_priority_fee)) => {
+                Some(max_priority_fee.min(max_fee.saturating_sub(base_fee)))
+            }
+            _ => Some(self.gas_price?.saturating_sub(base_fee)),
+        }
+    }
+
+    /// The 4-byte function selector at the start of the input, or `None` if
+    /// the input is too short to contain one.
+    pub fn function_selector(&self) -> Option<[u8; 4]> {
+        function_selector(&self.input)
+    }
+
+    /// A short `Debug` representation of this transaction for logs
+    pub fn compact(&self) -> CompactTransaction<'_> {
+        CompactTransaction(self)
+    }
+
+    /// The size of the transaction's input in bytes
+    pub fn input_size(&self) -> usize {
+        self.input.0.len()
+    }
+
+    /// The intrinsic gas charged for the transaction's input: 4 gas for
+    /// every zero byte and 16 gas for every nonzero byte
+    pub fn calldata_gas_cost(&self) -> u64 {
+        self.input
+            .0
+            .iter()
+            .map(|byte| if *byte == 0 { 4 } else { 16 })
+            .sum()
+    }
+
+    /// A request for `eth_call` that executes this transaction. EIP-1559
+    /// transactions are sent with their fee caps and without a gas price so
+    /// that nodes do not reject the request for specifying both
+    pub fn to_call_request(&self) -> CallRequest {
+        let dynamic_fee = self.max_fee_per_gas.is_some();
+        CallRequest {
+            from: self.from,
+            to: self.to,
+            gas: Some(self.gas),
+            gas_price: if dynamic_fee { None } else { self.gas_price },
+            value: Some(self.value),
+            data: Some(self.input.clone()),
+            transaction_type: self.transaction_type,
+            access_list: self.access_list.clone(),
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+        }
+    }
+
+    /// The sender of this transaction. If `from` is not known, the sender is
+    /// recovered from the signature. Recovery only works for legacy
+    /// transactions; the signing hash of typed transactions depends on data
+    /// that is not kept here, and `None` is returned for them.
+    ///
+    /// Legacy transactions with a `v` of 27 or 28 were signed without
+    /// replay protection and are recovered as such. Other values of `v`
+    /// encode the chain id following EIP-155; if `chain_id` is given, such
+    /// transactions must have been signed for that chain
+    pub fn recover_sender(&self, chain_id: Option<u64>) -> Option<Address> {
+        if self.from.is_some() {
+            return self.from;
+        }
+        if !self.is_legacy() {
+            return None;
+        }
+
+        let v = self.v?.as_u64();
+        let (signed_chain_id, recovery_id) = match v {
+            27 | 28 => (None, v - 27),
+            // EIP-155: v = chain_id * 2 + 35 + recovery_id
+            v if v >= 35 => (Some((v - 35) / 2), (v - 35) % 2),
+            _ => return None,
+        };
+        if let (Some(expected), Some(signed)) = (chain_id, signed_chain_id) {
+            if expected != signed {
+                return None;
+            }
+        }
+
+        let mut stream = rlp::RlpStream::new_list(if signed_chain_id.is_some() { 9 } else { 6 });
+        stream.append(&self.nonce);
+        stream.append(&self.gas_price?);
+        stream.append(&self.gas);
+        match &self.to {
+            Some(to) => stream.append(to),
+            None => stream.append_empty_data(),
+        };
+        stream.append(&self.value);
+        stream.append(&self.input.0);
+        if let Some(signed_chain_id) = signed_chain_id {
+            stream.append(&signed_chain_id);
+            stream.append(&0u8);
+            stream.append(&0u8);
+        }
+        let hash = tiny_keccak::keccak256(stream.as_raw());
+
+        let mut signature = [0u8; 64];
+        self.r?.to_big_endian(&mut signature[..32]);
+        self.s?.to_big_endian(&mut signature[32..]);
+        web3::signing::recover(&hash, &signature, recovery_id as i32).ok()
+    }
+
+    /// The distinct addresses in this transaction's access list, in the
+    /// order in which they first appear.
+    pub fn accessed_addresses(&self) -> Vec<Address> {
+        let mut addresses: Vec<Address> = Vec::new();
+        for item in self.access_list.iter().flatten() {
+            if !addresses.contains(&item.address) {
+                addresses.push(item.address);
+            }
+        }
+        addresses
+    }
+}
+
+/// A `Debug` representation of a transaction for logs: it shows the hash
+/// shortened to `0x1234…abcd`, the function selector, the value and the
+/// size of the input instead of the full transaction. Created by
+/// `LightTransaction::compact`
+pub struct CompactTransaction<'a>(pub &'a LightTransaction);
+
+impl<'a> fmt::Debug for CompactTransaction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tx = self.0;
+        let selector = tx
+            .function_selector()
+            .map(|selector| format!("0x{}", hex::encode(selector)));
+        f.debug_struct("LightTransaction")
+            .field("hash", &format_args!("{}", tx.hash))
+            .field(
+                "selector",
+                &format_args!("{}", selector.as_deref().unwrap_or("none")),
+            )
+            .field("value", &format_args!("{}", tx.value))
+            .field("input_len", &tx.input_size())
+            .finish()
+    }
+}
+
+/// A transaction that is compared and hashed by its `hash` alone, so that
+/// the same transaction appearing in two blocks during a reorg can be
+/// deduplicated with a `HashSet`. All other fields, including the
+/// transaction index and fees, are intentionally ignored
+#[derive(Clone, Debug)]
+pub struct TxByHash(pub LightTransaction);
+
+impl PartialEq for TxByHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.hash == other.0.hash
+    }
+}
+
+impl Eq for TxByHash {}
+
+impl std::hash::Hash for TxByHash {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash.hash(state)
+    }
 }
 
 impl From<Transaction> for LightTransaction {
@@ -47,6 +321,46 @@ impl From<Transaction> for LightTransaction {
             gas_price: tx.gas_price,
             gas: tx.gas,
             input: tx.input,
+            v: tx.v,
+            r: tx.r,
+            s: tx.s,
+            transaction_type: tx.transaction_type,
+            max_fee_per_gas: tx.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            access_list: tx.access_list,
+            // The web3 transaction type does not carry the EIP-4844 fields
+            max_fee_per_blob_gas: None,
+            blob_versioned_hashes: None,
+        }
+    }
+}
+
+/// Converts back to a web3 transaction. This is lossy: `LightTransaction`
+/// does not keep `block_hash`, `block_number` and `raw`,
+/// which are set to `None`, and the EIP-4844 blob fields are dropped since
+/// the web3 transaction can not hold them
+impl From<LightTransaction> for Transaction {
+    fn from(tx: LightTransaction) -> Self {
+        Self {
+            hash: tx.hash,
+            nonce: tx.nonce,
+            block_hash: None,
+            block_number: None,
+            transaction_index: tx.transaction_index,
+            from: tx.from,
+            to: tx.to,
+            value: tx.value,
+            gas_price: tx.gas_price,
+            gas: tx.gas,
+            input: tx.input,
+            v: tx.v,
+            r: tx.r,
+            s: tx.s,
+            raw: None,
+            transaction_type: tx.transaction_type,
+            access_list: tx.access_list,
+            max_fee_per_gas: tx.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
         }
     }
 }
@@ -63,6 +377,16 @@ impl From<&Transaction> for LightTransaction {
             gas_price: tx.gas_price,
             gas: tx.gas,
             input: tx.input.clone(),
+            v: tx.v,
+            r: tx.r,
+            s: tx.s,
+            transaction_type: tx.transaction_type,
+            max_fee_per_gas: tx.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            access_list: tx.access_list.clone(),
+            // The web3 transaction type does not carry the EIP-4844 fields
+            max_fee_per_blob_gas: None,
+            blob_versioned_hashes: None,
         }
     }
 }
@@ -74,39 +398,348 @@ pub type LightEthereumBlockV2 = Block<LightTransaction>;
 pub type LightEthereumBlock = LightEthereumBlockV2;
 
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct StoreTransactionReceipt {
     /// Transaction hash.
-    #[serde(rename = "transactionHash")]
+    #[serde(rename = "transactionHash", alias = "transaction_hash")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub transaction_hash: H256,
     /// Index within the block.
-    #[serde(rename = "transactionIndex")]
+    #[serde(rename = "transactionIndex", alias = "transaction_index")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub transaction_index: Index,
     /// Hash of the block this transaction was included within.
-    #[serde(rename = "blockHash")]
+    #[serde(rename = "blockHash", alias = "block_hash")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub block_hash: Option<H256>,
     /// Number of the block this transaction was included within.
-    #[serde(rename = "blockNumber")]
+    #[serde(
+        rename = "blockNumber",
+        alias = "block_number",
+        default,
+        with = "flexible_u64::option"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub block_number: Option<U64>,
     /// Cumulative gas used within the block after this was executed.
-    #[serde(rename = "cumulativeGasUsed")]
+    #[serde(rename = "cumulativeGasUsed", alias = "cumulative_gas_used")]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub cumulative_gas_used: U256,
     /// Gas used by this transaction alone.
     ///
     /// Gas used is `None` if the the client is running in light client mode.
-    #[serde(rename = "gasUsed")]
+    #[serde(rename = "gasUsed", alias = "gas_used")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub gas_used: Option<U256>,
     /// Contract address created, or `None` if not a deployment.
-    #[serde(rename = "contractAddress")]
+    #[serde(rename = "contractAddress", alias = "contract_address")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub contract_address: Option<Address>,
     /// Logs generated within this transaction.
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<serde_json::Value>"))]
     pub logs: Vec<Log>,
     /// Status: either 1 (success) or 0 (failure).
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub status: Option<U64>,
     /// State root.
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
     pub root: Option<H256>,
-    /// Logs bloom
-    #[serde(rename = "logsBloom")]
+    /// Logs bloom. Some clients (e.g., Erigon) omit it, in which case it is
+    /// empty
+    #[serde(rename = "logsBloom", alias = "logs_bloom", default)]
+    #[cfg_attr(feature = "schema", schemars(with = "String"))]
     pub logs_bloom: H2048,
+    /// The price per gas actually paid for this transaction.
+    #[serde(
+        rename = "effectiveGasPrice",
+        alias = "effective_gas_price",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub effective_gas_price: Option<U256>,
+    /// Transaction type (EIP-2718)
+    #[serde(
+        rename = "type",
+        alias = "transaction_type",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub transaction_type: Option<U64>,
+    /// Blob gas used by a blob transaction (EIP-4844)
+    #[serde(
+        rename = "blobGasUsed",
+        alias = "blob_gas_used",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub blob_gas_used: Option<U256>,
+    /// The price per blob gas paid by a blob transaction (EIP-4844)
+    #[serde(
+        rename = "blobGasPrice",
+        alias = "blob_gas_price",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub blob_gas_price: Option<U256>,
+}
+
+/// (De)serialization for `U64` values that some nodes send as decimal
+/// strings or JSON numbers rather than as hex strings. Deserialization
+/// accepts `"0x10"`, `"16"` and `16`; serialization always produces the
+/// canonical hex string. Binary formats use the plain `U64` encoding
+pub mod flexible_u64 {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+    use web3::types::U64;
+
+    pub fn serialize<S: Serializer>(value: &U64, serializer: S) -> Result<S::Ok, S::Error> {
+        value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U64, D::Error> {
+        if !deserializer.is_human_readable() {
+            return U64::deserialize(deserializer);
+        }
+        let value = Value::deserialize(deserializer)?;
+        from_value(&value).map_err(D::Error::custom)
+    }
+
+    fn from_value(value: &Value) -> Result<U64, String> {
+        match value {
+            Value::Number(number) => number
+                .as_u64()
+                .map(U64::from)
+                .ok_or_else(|| format!("invalid number: {}", number)),
+            Value::String(s) => {
+                let parsed = match s.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => s.parse::<u64>(),
+                };
+                parsed
+                    .map(U64::from)
+                    .map_err(|e| format!("invalid number `{}`: {}", s, e))
+            }
+            _ => Err(format!("invalid number: {}", value)),
+        }
+    }
+
+    /// Like the enclosing module, but for `Option<U64>`, where `null` is
+    /// `None`
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<U64>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            value.serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<U64>, D::Error> {
+            if !deserializer.is_human_readable() {
+                return Option::<U64>::deserialize(deserializer);
+            }
+            match Value::deserialize(deserializer)? {
+                Value::Null => Ok(None),
+                value => from_value(&value).map(Some).map_err(D::Error::custom),
+            }
+        }
+    }
+}
+
+/// (De)serialization of an `EthereumBlock` that writes the transactions
+/// of the block as a list of their hashes, for storage that keeps the full
+/// transactions elsewhere. Usable as `#[serde(with = "tx_hashes_only")]`.
+///
+/// This is lossy: deserializing produces transactions that only have their
+/// `hash` set. The header and the receipts are kept in full. Since the
+/// header passes through a `serde_json::Value`, deserialization needs a
+/// self-describing format like JSON
+pub mod tx_hashes_only {
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+    use std::sync::Arc;
+    use web3::types::H256;
+
+    use super::{EthereumBlock, LightEthereumBlock, LightTransaction, StoreTransactionReceipt};
+
+    #[derive(Serialize)]
+    struct HashesOnlyRef<'a> {
+        block: Value,
+        transaction_receipts: &'a [Arc<StoreTransactionReceipt>],
+    }
+
+    #[derive(Deserialize)]
+    struct HashesOnly {
+        block: Value,
+        transaction_receipts: Vec<Arc<StoreTransactionReceipt>>,
+    }
+
+    pub fn serialize<S: Serializer>(
+        block: &EthereumBlock,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let hashes: Vec<H256> = block.block.transactions.iter().map(|tx| tx.hash).collect();
+        let mut header = serde_json::to_value(block.block.as_ref()).map_err(ser::Error::custom)?;
+        header["transactions"] = serde_json::to_value(hashes).map_err(ser::Error::custom)?;
+
+        HashesOnlyRef {
+            block: header,
+            transaction_receipts: &block.transaction_receipts,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<EthereumBlock, D::Error> {
+        let HashesOnly {
+            mut block,
+            transaction_receipts,
+        } = HashesOnly::deserialize(deserializer)?;
+
+        let hashes: Vec<H256> =
+            serde_json::from_value(block["transactions"].take()).map_err(de::Error::custom)?;
+        block["transactions"] = Value::Array(Vec::new());
+        let mut block: LightEthereumBlock =
+            serde_json::from_value(block).map_err(de::Error::custom)?;
+        block.transactions = hashes
+            .into_iter()
+            .map(|hash| LightTransaction {
+                hash,
+                ..Default::default()
+            })
+            .collect();
+
+        Ok(EthereumBlock {
+            block: Arc::new(block),
+            transaction_receipts,
+        })
+    }
+}
+
+impl StoreTransactionReceipt {
+    /// Parse a batch of receipts as returned by a JSON-RPC provider, either
+    /// as a bare array or wrapped in a `{"result": [...]}` response
+    pub fn many_from_json(value: &serde_json::Value) -> Result<Vec<Self>, serde_json::Error> {
+        let receipts = value.get("result").unwrap_or(value);
+        Vec::<Self>::deserialize(receipts)
+    }
+
+    /// The fee paid for this transaction, i.e., `effective_gas_price *
+    /// gas_used`, or `None` if either of them is not known or the fee does
+    /// not fit into a `U256`
+    pub fn fee_paid(&self) -> Option<U256> {
+        self.checked_fee_paid()
+    }
+
+    /// Like `fee_paid`, but spells out that an overflowing fee, which only
+    /// adversarial receipts can produce, results in `None` rather than a
+    /// panic
+    pub fn checked_fee_paid(&self) -> Option<U256> {
+        self.effective_gas_price?.checked_mul(self.gas_used?)
+    }
+
+    /// The fee paid for the blobs of a blob transaction, i.e.,
+    /// `blob_gas_used * blob_gas_price`, or `None` if the receipt has no
+    /// blob fields or the fee does not fit into a `U256`
+    pub fn blob_fee_paid(&self) -> Option<U256> {
+        self.blob_gas_used?.checked_mul(self.blob_gas_price?)
+    }
+
+    /// The logs bloom computed from the addresses and topics of this
+    /// receipt's logs
+    pub fn computed_logs_bloom(&self) -> H2048 {
+        let mut bloom = H2048::zero();
+        for log in &self.logs {
+            let topics = log.topics.iter().map(|topic| topic.as_bytes());
+            for input in std::iter::once(log.address.as_bytes()).chain(topics) {
+                for (byte, mask) in bloom_bits(input) {
+                    bloom.0[byte] |= mask;
+                }
+            }
+        }
+        bloom
+    }
+
+    /// Whether the stored logs bloom contains every bit of the bloom
+    /// computed from the logs. A bloom that is missing bits indicates that
+    /// the receipt is corrupt
+    pub fn bloom_is_consistent(&self) -> bool {
+        let computed = self.computed_logs_bloom();
+        computed
+            .0
+            .iter()
+            .zip(self.logs_bloom.0.iter())
+            .all(|(computed, stored)| computed & !stored == 0)
+    }
+
+    /// Whether the transaction succeeded. Receipts without a status are
+    /// assumed to have succeeded
+    pub fn succeeded(&self) -> bool {
+        evaluate_transaction_status(self.status)
+    }
+
+    /// Whether the transaction failed
+    pub fn failed(&self) -> bool {
+        !self.succeeded()
+    }
+
+    /// Whether the transaction has the EIP-2718 type `t`, e.g., `3` for blob
+    /// transactions. Receipts without a type are legacy receipts of type `0`
+    pub fn is_type(&self, t: u64) -> bool {
+        self.transaction_type.unwrap_or_default() == U64::from(t)
+    }
+
+    /// Whether this receipt came from a client running in light client
+    /// mode, which does not report `gas_used`
+    pub fn is_light_client_receipt(&self) -> bool {
+        self.gas_used.is_none()
+    }
+
+    /// The address of the contract created by this transaction, if any
+    pub fn created_contract(&self) -> Option<Address> {
+        self.contract_address
+    }
+
+    /// The logs whose first topic, i.e., whose event signature, is `sig`.
+    /// Logs without topics never match
+    pub fn logs_for_topic0(&self, sig: H256) -> impl Iterator<Item = &Log> {
+        self.logs
+            .iter()
+            .filter(move |log| log.topics.first() == Some(&sig))
+    }
+
+    /// The logs whose `data` starts with `prefix`, for cheap filtering
+    /// before decoding. Logs with less data than `prefix` never match
+    pub fn logs_with_data_prefix<'a>(
+        &'a self,
+        prefix: &'a [u8],
+    ) -> impl Iterator<Item = &'a Log> + 'a {
+        self.logs
+            .iter()
+            .filter(move |log| log.data.0.starts_with(prefix))
+    }
+
+    /// The logs of this receipt together with their log index. Logs that do
+    /// not have a `log_index` use their position in the receipt instead
+    pub fn logs_with_index(&self) -> impl Iterator<Item = (U256, &Log)> {
+        self.logs
+            .iter()
+            .enumerate()
+            .map(|(pos, log)| (log.log_index.unwrap_or_else(|| U256::from(pos)), log))
+    }
+
+    /// The logs of this receipt that have not been removed by a reorg
+    pub fn non_removed_logs(&self) -> impl Iterator<Item = &Log> {
+        self.logs.iter().filter(|log| log.removed != Some(true))
+    }
 }
 
 impl From<TransactionReceipt> for StoreTransactionReceipt {
@@ -123,6 +756,34 @@ impl From<TransactionReceipt> for StoreTransactionReceipt {
             status: receipt.status,
             root: receipt.root,
             logs_bloom: receipt.logs_bloom,
+            effective_gas_price: receipt.effective_gas_price,
+            transaction_type: receipt.transaction_type,
+            // The web3 receipt does not have the EIP-4844 fields
+            blob_gas_used: None,
+            blob_gas_price: None,
+        }
+    }
+}
+
+impl From<&TransactionReceipt> for StoreTransactionReceipt {
+    fn from(receipt: &TransactionReceipt) -> StoreTransactionReceipt {
+        Self {
+            transaction_hash: receipt.transaction_hash,
+            transaction_index: receipt.transaction_index,
+            block_hash: receipt.block_hash,
+            block_number: receipt.block_number,
+            cumulative_gas_used: receipt.cumulative_gas_used,
+            gas_used: receipt.gas_used,
+            contract_address: receipt.contract_address,
+            logs: receipt.logs.clone(),
+            status: receipt.status,
+            root: receipt.root,
+            logs_bloom: receipt.logs_bloom,
+            effective_gas_price: receipt.effective_gas_price,
+            transaction_type: receipt.transaction_type,
+            // The web3 receipt does not have the EIP-4844 fields
+            blob_gas_used: None,
+            blob_gas_price: None,
         }
     }
 }
@@ -165,8 +826,64 @@ impl LightEthereumBlockFromV1To<LightEthereumBlock> for LightEthereumBlock {
     }
 }
 
-#[derive(Debug)]
-struct ConversionError;
+pub trait LightEthereumBlockToV1 {
+    /// Converts to a V1 block for writing to older caches or APIs. The
+    /// block header is kept as is, but transactions lose the fields that
+    /// `LightTransaction` does not keep; see `From<LightTransaction> for
+    /// Transaction`
+    fn to_v1(self) -> LightEthereumBlockV1;
+}
+
+impl LightEthereumBlockToV1 for LightEthereumBlock {
+    fn to_v1(self) -> LightEthereumBlockV1 {
+        LightEthereumBlockV1 {
+            hash: self.hash,
+            parent_hash: self.parent_hash,
+            uncles_hash: self.uncles_hash,
+            author: self.author,
+            state_root: self.state_root,
+            transactions_root: self.transactions_root,
+            receipts_root: self.receipts_root,
+            number: self.number,
+            gas_used: self.gas_used,
+            gas_limit: self.gas_limit,
+            base_fee_per_gas: self.base_fee_per_gas,
+            extra_data: self.extra_data,
+            logs_bloom: self.logs_bloom,
+            timestamp: self.timestamp,
+            difficulty: self.difficulty,
+            total_difficulty: self.total_difficulty,
+            seal_fields: self.seal_fields,
+            uncles: self.uncles,
+            transactions: self
+                .transactions
+                .into_iter()
+                .map(Transaction::from)
+                .collect(),
+            size: self.size,
+            mix_hash: self.mix_hash,
+            nonce: self.nonce,
+        }
+    }
+}
+
+/// Converting a V1 block failed because one of its fields could not be
+/// converted
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("failed to convert block field `{field}`: {reason}")]
+pub struct ConversionError {
+    pub field: &'static str,
+    pub reason: String,
+}
+
+impl ConversionError {
+    pub fn new(field: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            field,
+            reason: reason.into(),
+        }
+    }
+}
 
 pub trait LightEthereumBlockTryFromV1To<T> {
     fn try_from(block: LightEthereumBlockV1) -> T;
@@ -175,26 +892,261 @@ pub trait LightEthereumBlockTryFromV1To<T> {
 impl LightEthereumBlockTryFromV1To<Result<LightEthereumBlock, ConversionError>>
     for LightEthereumBlock
 {
+    /// Converts a V1 block, rejecting pending blocks, i.e., blocks without
+    /// a number or a hash
     fn try_from(block: LightEthereumBlockV1) -> Result<LightEthereumBlock, ConversionError> {
+        if block.number.is_none() {
+            return Err(ConversionError::new(
+                "number",
+                "block has no number; it may be pending",
+            ));
+        }
+        if block.hash.is_none() {
+            return Err(ConversionError::new(
+                "hash",
+                "block has no hash; it may be pending",
+            ));
+        }
         Ok(<LightEthereumBlock as LightEthereumBlockFromV1To<
             LightEthereumBlock,
         >>::from_v1(block))
     }
 }
 
+/// The pointer to a block as returned by RPC calls that only include
+/// transaction hashes. Pending blocks, which have no hash or number, are
+/// rejected.
+///
+/// This is not a `TryFrom` impl since the `From<Block<T>>` impl for
+/// `BlockPtr`, which panics for pending blocks, already implies one
+pub fn block_ptr_from_hash_block(block: &Block<H256>) -> Result<BlockPtr, EthereumTypeError> {
+    let number = block.number.ok_or(EthereumTypeError::MissingBlockNumber)?;
+    let hash = block.hash.ok_or(EthereumTypeError::MissingBlockHash)?;
+    let number = BlockNumber::try_from(number.as_u64())
+        .map_err(|_| EthereumTypeError::BlockNumberOutOfRange(number))?;
+    Ok(BlockPtr::from((hash, number)))
+}
+
+/// The metadata of a block, for callers that do not need its transactions.
+/// The hash and number are `None` for pending blocks
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub hash: Option<H256>,
+    #[serde(rename = "parentHash")]
+    pub parent_hash: H256,
+    pub number: Option<U64>,
+    pub timestamp: U256,
+}
+
+impl BlockHeader {
+    /// Extract only the header fields from the JSON for a block, without
+    /// parsing its transactions
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        Self::deserialize(value)
+    }
+}
+
+/// A validator withdrawal from the beacon chain (EIP-4895). The `amount`
+/// is in gwei, not wei
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "RawWithdrawal", into = "RawWithdrawal")]
+pub struct Withdrawal {
+    pub index: u64,
+    pub validator_index: u64,
+    pub address: Address,
+    pub amount: U256,
+}
+
+/// The JSON-RPC encoding of a `Withdrawal`, which has its numbers as hex
+/// strings
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawWithdrawal {
+    #[serde(with = "flexible_u64")]
+    index: U64,
+    #[serde(with = "flexible_u64")]
+    validator_index: U64,
+    address: Address,
+    amount: U256,
+}
+
+impl From<RawWithdrawal> for Withdrawal {
+    fn from(raw: RawWithdrawal) -> Self {
+        Withdrawal {
+            index: raw.index.as_u64(),
+            validator_index: raw.validator_index.as_u64(),
+            address: raw.address,
+            amount: raw.amount,
+        }
+    }
+}
+
+impl From<Withdrawal> for RawWithdrawal {
+    fn from(withdrawal: Withdrawal) -> Self {
+        RawWithdrawal {
+            index: U64::from(withdrawal.index),
+            validator_index: U64::from(withdrawal.validator_index),
+            address: withdrawal.address,
+            amount: withdrawal.amount,
+        }
+    }
+}
+
+/// The withdrawals of a block (EIP-4895).
+///
+/// These can not be methods on `LightEthereumBlockExt`: the web3 `Block`
+/// that `LightEthereumBlock` aliases has no `withdrawals` field, so they are
+/// dropped whenever a block is parsed, whether from an RPC response, the
+/// block cache or Firehose. A `LightEthereumBlock` never has them, and
+/// `withdrawals`, `total_withdrawn` and `withdrawals_to` live here instead.
+/// Read them from the block's JSON as returned by the node with
+/// `BlockWithdrawals::from_json`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct BlockWithdrawals {
+    pub hash: Option<H256>,
+    pub number: Option<U64>,
+    /// `None` for blocks before Shanghai
+    #[serde(default)]
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl BlockWithdrawals {
+    /// Extract the hash, number and withdrawals from the JSON for a block
+    pub fn from_json(value: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        Self::deserialize(value)
+    }
+
+    /// The withdrawals of the block; empty for blocks before Shanghai
+    pub fn withdrawals(&self) -> Vec<Withdrawal> {
+        self.withdrawals.clone().unwrap_or_default()
+    }
+
+    /// The total amount withdrawn in the block, in gwei. Zero for blocks
+    /// before Shanghai. The total saturates at `U256::MAX`
+    pub fn total_withdrawn(&self) -> U256 {
+        self.iter().fold(U256::zero(), |total, withdrawal| {
+            total.saturating_add(withdrawal.amount)
+        })
+    }
+
+    /// The withdrawals in the block that were paid to `address`
+    pub fn withdrawals_to(&self, address: Address) -> Vec<Withdrawal> {
+        self.iter()
+            .filter(|withdrawal| withdrawal.address == address)
+            .cloned()
+            .collect()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Withdrawal> {
+        self.withdrawals.iter().flatten()
+    }
+}
+
+/// Formats a block as `#number (hash)`, or `none (-)` for a pending block
+pub struct DisplayBlock<'a>(pub &'a LightEthereumBlock);
+
+impl<'a> fmt::Display for DisplayBlock<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.number {
+            Some(number) => write!(f, "#{}", number)?,
+            None => write!(f, "none")?,
+        }
+        match self.0.hash {
+            Some(hash) => write!(f, " ({:x})", hash),
+            None => write!(f, " (-)"),
+        }
+    }
+}
+
+/// Withdrawals are not available through this trait since a
+/// `LightEthereumBlock` can not hold them; see `BlockWithdrawals`
 pub trait LightEthereumBlockExt {
     fn number(&self) -> BlockNumber;
+    fn try_number(&self) -> Result<BlockNumber, EthereumTypeError>;
+    /// The block number, or `default` if the block is pending or its number
+    /// does not fit into a `BlockNumber`. For logging and similar uses where
+    /// a sentinel is good enough
+    fn number_or(&self, default: BlockNumber) -> BlockNumber;
     fn transaction_for_log(&self, log: &Log) -> Option<LightTransaction>;
     fn transaction_for_call(&self, call: &EthereumCall) -> Option<LightTransaction>;
     fn parent_ptr(&self) -> Option<BlockPtr>;
     fn format(&self) -> String;
+    fn display(&self) -> DisplayBlock<'_>;
+    /// The block's hash and number, or `None` if the block is pending
+    fn as_tuple(&self) -> Option<(H256, u64)>;
+    /// The pointer to this block. Panics if the block is pending, i.e. has
+    /// no hash or number; only use this on confirmed blocks and use
+    /// `try_block_ptr` for blocks that might be pending.
     fn block_ptr(&self) -> BlockPtr;
+    /// The pointer to this block, or `None` if the block is pending.
+    fn try_block_ptr(&self) -> Option<BlockPtr>;
+    /// The block's timestamp. Timestamps that can not be represented as a
+    /// `BlockTime` are clamped to `BlockTime::MAX`
     fn timestamp(&self) -> BlockTime;
+    fn try_timestamp(&self) -> Result<BlockTime, anyhow::Error>;
+    /// The block's timestamp in seconds since the Unix epoch, clamped to
+    /// `u64::MAX`
+    fn unix_timestamp(&self) -> u64;
+    /// The block's timestamp, or `None` if it can not be represented as a
+    /// `DateTime`
+    fn datetime(&self) -> Option<chrono::DateTime<chrono::Utc>>;
+    /// How long before `now` this block was produced, or `None` if the
+    /// block's timestamp is after `now`
+    fn age(&self, now: BlockTime) -> Option<chrono::Duration>;
+    /// Checks the block's logs bloom for `address`. This can return false
+    /// positives, but never false negatives: if it returns `false`, no log
+    /// in the block was emitted by `address`. Blocks without a logs bloom
+    /// might contain anything.
+    fn bloom_might_contain_address(&self, address: &Address) -> bool;
+    /// Checks the block's logs bloom for `topic`, with the same caveats as
+    /// `bloom_might_contain_address`
+    fn bloom_might_contain_topic(&self, topic: &H256) -> bool;
+    /// Whether `parent` is this block's parent. The genesis block and
+    /// pending blocks are not the child of any block
+    fn is_child_of(&self, parent: &BlockPtr) -> bool;
+    /// Whether `ptr` points to this block. Pending blocks never match
+    fn is_same_block_as(&self, ptr: &BlockPtr) -> bool;
+    /// Whether `other` is a different block at the same height as this
+    /// block, which indicates a reorg. Pending blocks never conflict
+    fn conflicts_with(&self, other: &BlockPtr) -> bool;
+    /// How many blocks `head` is past this block; a block at the head has
+    /// zero confirmations. `None` if this block is ahead of `head` or
+    /// pending
+    fn confirmations(&self, head: &BlockPtr) -> Option<BlockNumber>;
+    /// Whether this block has at least `required` confirmations
+    fn is_final(&self, head: &BlockPtr, required: BlockNumber) -> bool;
+    /// The total difficulty of the chain up to and including this block,
+    /// if the node reported it. Only meaningful for proof-of-work blocks
+    fn total_difficulty(&self) -> Option<U256>;
+    fn transaction_count(&self) -> usize;
+    fn has_transactions(&self) -> bool;
+    /// A cheap consistency check: a block with transactions must have a
+    /// nonzero `transactions_root`. Blocks without transactions always pass
+    fn expected_transactions_root_present(&self) -> bool;
+    /// The hashes of this block's uncles (ommers)
+    fn uncle_hashes(&self) -> &[H256];
+    fn has_uncles(&self) -> bool;
+    /// The fraction of the gas limit that the block used, clamped to `[0, 1]`,
+    /// or `None` if the gas limit is zero
+    fn gas_utilization(&self) -> Option<f64>;
+    /// The base fee of the next block according to EIP-1559, or `None` for
+    /// blocks before London, which have no base fee
+    fn predicted_next_base_fee(&self) -> Option<U256>;
 }
 
 impl LightEthereumBlockExt for LightEthereumBlock {
     fn number(&self) -> BlockNumber {
-        BlockNumber::try_from(self.number.unwrap().as_u64()).unwrap()
+        self.try_number().expect("block has a valid number")
+    }
+
+    fn try_number(&self) -> Result<BlockNumber, EthereumTypeError> {
+        let number = self.number.ok_or(EthereumTypeError::MissingBlockNumber)?;
+        BlockNumber::try_from(number.as_u64())
+            .map_err(|_| EthereumTypeError::BlockNumberOutOfRange(number))
+    }
+
+    fn number_or(&self, default: BlockNumber) -> BlockNumber {
+        self.try_number().unwrap_or(default)
     }
 
     fn transaction_for_log(&self, log: &Log) -> Option<LightTransaction> {
@@ -217,61 +1169,512 @@ impl LightEthereumBlockExt for LightEthereumBlock {
     }
 
     fn format(&self) -> String {
-        format!(
-            "{} ({})",
-            self.number
-                .map_or(String::from("none"), |number| format!("#{}", number)),
-            self.hash
-                .map_or(String::from("-"), |hash| format!("{:x}", hash))
-        )
+        self.display().to_string()
+    }
+
+    fn display(&self) -> DisplayBlock<'_> {
+        DisplayBlock(self)
+    }
+
+    fn as_tuple(&self) -> Option<(H256, u64)> {
+        Some((self.hash?, self.number?.as_u64()))
     }
 
     fn block_ptr(&self) -> BlockPtr {
-        BlockPtr::from((self.hash.unwrap(), self.number.unwrap().as_u64()))
+        BlockPtr::from(self.as_tuple().expect("block is not pending"))
+    }
+
+    fn try_block_ptr(&self) -> Option<BlockPtr> {
+        self.as_tuple().map(BlockPtr::from)
     }
 
     fn timestamp(&self) -> BlockTime {
-        let ts = i64::try_from(self.timestamp.as_u64()).unwrap();
-        BlockTime::since_epoch(ts, 0)
+        self.try_timestamp().unwrap_or(BlockTime::MAX)
     }
-}
 
-#[derive(Clone, Debug)]
-pub struct EthereumBlockWithCalls {
-    pub ethereum_block: EthereumBlock,
-    /// The calls in this block; `None` means we haven't checked yet,
-    /// `Some(vec![])` means that we checked and there were none
-    pub calls: Option<Vec<EthereumCall>>,
-}
+    fn try_timestamp(&self) -> Result<BlockTime, anyhow::Error> {
+        if self.timestamp.bits() > 63 {
+            return Err(anyhow::anyhow!(
+                "block timestamp out of range: {}",
+                self.timestamp
+            ));
+        }
+        let ts = self.timestamp.low_u64() as i64;
+        // `BlockTime::since_epoch` panics for timestamps that are out of range
+        Timestamp::since_epoch(ts, 0)
+            .ok_or_else(|| anyhow::anyhow!("block timestamp out of range: {}", ts))?;
+        Ok(BlockTime::since_epoch(ts, 0))
+    }
 
-impl EthereumBlockWithCalls {
-    /// Given an `EthereumCall`, check within receipts if that transaction was successful.
-    pub fn transaction_for_call_succeeded(&self, call: &EthereumCall) -> anyhow::Result<bool> {
-        let call_transaction_hash = call.transaction_hash.ok_or(anyhow::anyhow!(
-            "failed to find a transaction for this call"
-        ))?;
+    fn unix_timestamp(&self) -> u64 {
+        if self.timestamp.bits() > 64 {
+            return u64::MAX;
+        }
+        self.timestamp.low_u64()
+    }
 
-        let receipt = self
-            .ethereum_block
-            .transaction_receipts
-            .iter()
-            .find(|txn| txn.transaction_hash == call_transaction_hash)
-            .ok_or(anyhow::anyhow!(
-                "failed to find the receipt for this transaction"
-            ))?;
+    fn datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let secs = i64::try_from(self.unix_timestamp()).ok()?;
+        chrono::DateTime::from_timestamp(secs, 0)
+    }
 
-        Ok(evaluate_transaction_status(receipt.status))
+    fn age(&self, now: BlockTime) -> Option<chrono::Duration> {
+        let secs = now
+            .as_secs_since_epoch()
+            .checked_sub(self.timestamp().as_secs_since_epoch())?;
+        if secs < 0 {
+            return None;
+        }
+        chrono::Duration::try_seconds(secs)
     }
-}
 
-/// Evaluates if a given transaction was successful.
+    fn bloom_might_contain_address(&self, address: &Address) -> bool {
+        self.logs_bloom
+            .map_or(true, |bloom| bloom_contains(&bloom, address.as_bytes()))
+    }
+
+    fn bloom_might_contain_topic(&self, topic: &H256) -> bool {
+        self.logs_bloom
+            .map_or(true, |bloom| bloom_contains(&bloom, topic.as_bytes()))
+    }
+
+    fn is_child_of(&self, parent: &BlockPtr) -> bool {
+        self.try_number().is_ok() && self.parent_ptr().as_ref() == Some(parent)
+    }
+
+    fn is_same_block_as(&self, ptr: &BlockPtr) -> bool {
+        self.try_block_ptr().as_ref() == Some(ptr)
+    }
+
+    fn conflicts_with(&self, other: &BlockPtr) -> bool {
+        match self.try_number() {
+            Ok(number) => number == other.number && self.hash != Some(other.hash_as_h256()),
+            Err(_) => false,
+        }
+    }
+
+    fn confirmations(&self, head: &BlockPtr) -> Option<BlockNumber> {
+        let number = self.try_number().ok()?;
+        (head.number >= number).then(|| head.number - number)
+    }
+
+    fn is_final(&self, head: &BlockPtr, required: BlockNumber) -> bool {
+        self.confirmations(head)
+            .map_or(false, |confirmations| confirmations >= required)
+    }
+
+    fn total_difficulty(&self) -> Option<U256> {
+        self.total_difficulty
+    }
+
+    fn transaction_count(&self) -> usize {
+        self.transactions.len()
+    }
+
+    fn has_transactions(&self) -> bool {
+        !self.transactions.is_empty()
+    }
+
+    fn expected_transactions_root_present(&self) -> bool {
+        !self.has_transactions() || !self.transactions_root.is_zero()
+    }
+
+    fn uncle_hashes(&self) -> &[H256] {
+        &self.uncles
+    }
+
+    fn has_uncles(&self) -> bool {
+        !self.uncles.is_empty()
+    }
+
+    fn gas_utilization(&self) -> Option<f64> {
+        if self.gas_limit.is_zero() {
+            return None;
+        }
+        let ratio = u256_to_f64(self.gas_used) / u256_to_f64(self.gas_limit);
+        Some(ratio.clamp(0.0, 1.0))
+    }
+
+    fn predicted_next_base_fee(&self) -> Option<U256> {
+        // Constants from EIP-1559
+        const ELASTICITY_MULTIPLIER: u64 = 2;
+        const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+
+        let base_fee = self.base_fee_per_gas?;
+        let target = self.gas_limit / ELASTICITY_MULTIPLIER;
+        if target.is_zero() || self.gas_used == target {
+            return Some(base_fee);
+        }
+
+        if self.gas_used > target {
+            let delta = base_fee.saturating_mul(self.gas_used - target)
+                / target
+                / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+            Some(base_fee.saturating_add(delta.max(U256::one())))
+        } else {
+            let delta = base_fee.saturating_mul(target - self.gas_used)
+                / target
+                / BASE_FEE_MAX_CHANGE_DENOMINATOR;
+            Some(base_fee.saturating_sub(delta))
+        }
+    }
+}
+
+fn u256_to_f64(value: U256) -> f64 {
+    value
+        .0
+        .iter()
+        .rev()
+        .fold(0.0, |acc, word| acc * 18446744073709551616.0 + *word as f64)
+}
+
+/// The three bits that `input` sets in a logs bloom, as pairs of byte index
+/// into the bloom and the mask for the bit in that byte
+fn bloom_bits(input: &[u8]) -> [(usize, u8); 3] {
+    let hash = tiny_keccak::keccak256(input);
+    let mut bits = [(0, 0); 3];
+    for (i, bit) in bits.iter_mut().enumerate() {
+        let index = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+        *bit = (255 - index / 8, 1 << (index % 8));
+    }
+    bits
+}
+
+fn bloom_contains(bloom: &H2048, input: &[u8]) -> bool {
+    bloom_bits(input)
+        .iter()
+        .all(|(byte, mask)| bloom.0[*byte] & mask != 0)
+}
+
+/// A block that is known to not be pending, i.e., that has a hash and a
+/// number. Unlike the corresponding methods on `LightEthereumBlockExt`, its
+/// accessors can not panic
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfirmedBlock(Arc<LightEthereumBlock>);
+
+impl ConfirmedBlock {
+    /// Wrap `block`, or return `None` if it is pending or its number is not
+    /// a valid `BlockNumber`
+    pub fn try_new(block: Arc<LightEthereumBlock>) -> Option<Self> {
+        if block.hash.is_none() || block.try_number().is_err() {
+            return None;
+        }
+        Some(ConfirmedBlock(block))
+    }
+
+    pub fn number(&self) -> BlockNumber {
+        self.0.number()
+    }
+
+    pub fn hash(&self) -> H256 {
+        self.0.hash.unwrap()
+    }
+
+    pub fn block_ptr(&self) -> BlockPtr {
+        BlockPtr::from((self.hash(), self.number()))
+    }
+
+    pub fn block(&self) -> &Arc<LightEthereumBlock> {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Arc<LightEthereumBlock> {
+        self.0
+    }
+}
+
+/// A block that is ordered by its number and then by its hash, e.g., for
+/// keeping blocks in a `BTreeSet`. Pending blocks, which have no number,
+/// sort after all other blocks. Two blocks with the same number and hash
+/// are considered equal, regardless of their other fields
+#[derive(Clone, Debug)]
+pub struct OrderedBlock(pub Arc<LightEthereumBlock>);
+
+impl OrderedBlock {
+    fn sort_key(&self) -> (bool, Option<U64>, Option<H256>) {
+        (self.0.number.is_none(), self.0.number, self.0.hash)
+    }
+}
+
+impl PartialEq for OrderedBlock {
+    fn eq(&self, other: &Self) -> bool {
+        self.sort_key() == other.sort_key()
+    }
+}
+
+impl Eq for OrderedBlock {}
+
+impl PartialOrd for OrderedBlock {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedBlock {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+/// Proof-of-work fork choice between two competing blocks: the block with
+/// the higher total difficulty wins. A block without a total difficulty
+/// loses against one with it. Ties go to the block with the lower hash so
+/// that the choice does not depend on the order of the arguments
+pub fn heavier_block<'a>(
+    a: &'a LightEthereumBlock,
+    b: &'a LightEthereumBlock,
+) -> &'a LightEthereumBlock {
+    use std::cmp::Ordering;
+
+    match a.total_difficulty().cmp(&b.total_difficulty()) {
+        Ordering::Greater => a,
+        Ordering::Less => b,
+        Ordering::Equal if b.hash < a.hash => b,
+        Ordering::Equal => a,
+    }
+}
+
+/// How a block fails to follow the block before it in a chain
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainGapKind {
+    /// The block's number is not one more than the previous block's number
+    NumberSkip,
+    /// The block's parent hash is not the hash of the previous block
+    ParentHashMismatch,
+    /// The block or the previous block is pending and can not be checked
+    Pending,
+}
+
+/// The block at `index` in a sequence of blocks does not follow the block
+/// before it
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+#[error("block at index {index} does not follow its predecessor: {kind:?}")]
+pub struct ChainGap {
+    pub index: usize,
+    pub kind: ChainGapKind,
+}
+
+/// Check that `blocks` form a chain, i.e., that each block is the child of
+/// the block before it
+pub fn validate_chain(blocks: &[Arc<LightEthereumBlock>]) -> Result<(), ChainGap> {
+    for (index, pair) in blocks.windows(2).enumerate() {
+        let index = index + 1;
+        let (prev, block) = (&pair[0], &pair[1]);
+        let gap = |kind| ChainGap { index, kind };
+
+        let prev_ptr = prev
+            .try_block_ptr()
+            .ok_or_else(|| gap(ChainGapKind::Pending))?;
+        let number = block.try_number().map_err(|_| gap(ChainGapKind::Pending))?;
+        if number != prev_ptr.number + 1 {
+            return Err(gap(ChainGapKind::NumberSkip));
+        }
+        if block.parent_hash != prev_ptr.hash_as_h256() {
+            return Err(gap(ChainGapKind::ParentHashMismatch));
+        }
+    }
+    Ok(())
+}
+
+/// Tracks progress through the block range `[next, end]`, e.g., for a
+/// backfill. The cursor can be serialized so that an interrupted backfill
+/// can resume where it left off
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRangeCursor {
+    /// The next block to process
+    pub next: BlockNumber,
+    /// The last block in the range
+    pub end: BlockNumber,
+}
+
+impl BlockRangeCursor {
+    pub fn new(start: BlockNumber, end: BlockNumber) -> Self {
+        Self { next: start, end }
+    }
+
+    /// Record that `block` has been processed. Fails, and leaves the cursor
+    /// unchanged, if `block` is pending or its number is out of range
+    pub fn advance(&mut self, block: &LightEthereumBlock) -> Result<(), EthereumTypeError> {
+        let number = block.try_number()?;
+        self.next = number.checked_add(1).ok_or_else(|| {
+            EthereumTypeError::BlockNumberOutOfRange(U64::from(number as u64 + 1))
+        })?;
+        Ok(())
+    }
+
+    /// Whether all blocks in the range have been processed
+    pub fn is_done(&self) -> bool {
+        self.next > self.end
+    }
+}
+
+/// Errors from looking up data in Ethereum blocks, so that callers can
+/// match on what went wrong
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum EthereumTypeError {
+    #[error("failed to find a transaction for this call")]
+    NoTransactionForCall,
+    #[error("failed to find the receipt for transaction {0:?}")]
+    NoReceiptForTransaction(H256),
+    #[error("block has no number; it may be pending")]
+    MissingBlockNumber,
+    #[error("block has no hash; it may be pending")]
+    MissingBlockHash,
+    #[error("block number out of range: {0}")]
+    BlockNumberOutOfRange(U64),
+}
+
+#[derive(Clone, Debug)]
+pub struct EthereumBlockWithCalls {
+    pub ethereum_block: EthereumBlock,
+    /// The calls in this block; `None` means we haven't checked yet,
+    /// `Some(vec![])` means that we checked and there were none
+    pub calls: Option<Vec<EthereumCall>>,
+}
+
+impl EthereumBlockWithCalls {
+    /// Given an `EthereumCall`, check within receipts if that transaction was successful.
+    ///
+    /// This scans the receipts linearly; to check many calls, use
+    /// `successful_calls` instead.
+    pub fn transaction_for_call_succeeded(
+        &self,
+        call: &EthereumCall,
+    ) -> Result<bool, EthereumTypeError> {
+        let call_transaction_hash = call
+            .transaction_hash
+            .ok_or(EthereumTypeError::NoTransactionForCall)?;
+
+        let receipt = self
+            .ethereum_block
+            .transaction_receipts
+            .iter()
+            .find(|txn| txn.transaction_hash == call_transaction_hash)
+            .ok_or(EthereumTypeError::NoReceiptForTransaction(
+                call_transaction_hash,
+            ))?;
+
+        Ok(evaluate_transaction_status(receipt.status))
+    }
+
+    /// The calls in this block whose transaction succeeded. Calls without a
+    /// transaction or without a receipt for their transaction are skipped.
+    pub fn successful_calls(&self) -> impl Iterator<Item = &EthereumCall> {
+        let status = self.transaction_status_map();
+
+        self.calls.iter().flatten().filter(move |call| {
+            call.transaction_hash
+                .and_then(|hash| status.get(&hash).copied())
+                .unwrap_or(false)
+        })
+    }
+
+    /// Split the calls in this block into those whose transaction succeeded
+    /// and those whose transaction failed, returned as `(succeeded, failed)`.
+    ///
+    /// Fails if a call has no transaction or there is no receipt for its
+    /// transaction.
+    pub fn partition_calls(
+        &self,
+    ) -> Result<(Vec<&EthereumCall>, Vec<&EthereumCall>), EthereumTypeError> {
+        let status = self.transaction_status_map();
+
+        let mut succeeded = Vec::new();
+        let mut failed = Vec::new();
+        for call in self.calls.iter().flatten() {
+            let call_transaction_hash = call
+                .transaction_hash
+                .ok_or(EthereumTypeError::NoTransactionForCall)?;
+            let ok = status.get(&call_transaction_hash).ok_or(
+                EthereumTypeError::NoReceiptForTransaction(call_transaction_hash),
+            )?;
+            if *ok {
+                succeeded.push(call);
+            } else {
+                failed.push(call);
+            }
+        }
+        Ok((succeeded, failed))
+    }
+
+    fn transaction_status_map(&self) -> HashMap<H256, bool> {
+        self.ethereum_block
+            .transaction_receipts
+            .iter()
+            .map(|receipt| {
+                (
+                    receipt.transaction_hash,
+                    evaluate_transaction_status(receipt.status),
+                )
+            })
+            .collect()
+    }
+
+    /// The gas used by all calls in this block. The total saturates at
+    /// `U256::MAX`
+    pub fn total_call_gas(&self) -> U256 {
+        self.calls
+            .iter()
+            .flatten()
+            .fold(U256::zero(), |total, call| {
+                total.saturating_add(call.gas_used)
+            })
+    }
+
+    /// The calls in this block made to `address`
+    pub fn calls_to(&self, address: Address) -> impl Iterator<Item = &EthereumCall> {
+        self.calls
+            .iter()
+            .flatten()
+            .filter(move |call| call.to == address)
+    }
+
+    /// The calls in this block made by `address`
+    pub fn calls_from(&self, address: Address) -> impl Iterator<Item = &EthereumCall> {
+        self.calls
+            .iter()
+            .flatten()
+            .filter(move |call| call.from == address)
+    }
+
+    /// The calls in this block that transferred a nonzero amount of ETH
+    pub fn value_transferring_calls(&self) -> impl Iterator<Item = &EthereumCall> {
+        self.calls
+            .iter()
+            .flatten()
+            .filter(|call| call.transfers_value())
+    }
+}
+
+/// The outcome of a transaction as recorded in its receipt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionStatus {
+    Succeeded,
+    Failed,
+    /// The receipt has no status field; receipts before Byzantium
+    /// (EIP-658) do not record one
+    Unknown,
+}
+
+/// Determine the status of a transaction from the status field of its
+/// receipt
+pub fn transaction_status(receipt_status: Option<U64>) -> TransactionStatus {
+    match receipt_status {
+        Some(status) if status.is_zero() => TransactionStatus::Failed,
+        Some(_) => TransactionStatus::Succeeded,
+        None => TransactionStatus::Unknown,
+    }
+}
+
+/// Evaluates if a given transaction was successful.
 ///
 /// Returns `true` on success and `false` on failure.
 /// If a receipt does not have a status value (EIP-658), assume the transaction was successful.
 pub fn evaluate_transaction_status(receipt_status: Option<U64>) -> bool {
-    receipt_status
-        .map(|status| !status.is_zero())
-        .unwrap_or(true)
+    match transaction_status(receipt_status) {
+        TransactionStatus::Succeeded | TransactionStatus::Unknown => true,
+        TransactionStatus::Failed => false,
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
@@ -281,11 +1684,39 @@ pub struct EthereumBlockV1 {
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct EthereumBlockV2 {
+    #[cfg_attr(feature = "schema", schemars(with = "LightEthereumBlockSchema"))]
     pub block: Arc<LightEthereumBlock>,
     pub transaction_receipts: Vec<Arc<StoreTransactionReceipt>>,
 }
 
+/// Stands in for `LightEthereumBlock` in the JSON schema since the web3
+/// block type does not implement `JsonSchema`. Only the fields we rely on
+/// are described; the remaining header fields are allowed but unchecked
+#[cfg(feature = "schema")]
+#[derive(schemars::JsonSchema)]
+#[schemars(rename = "LightEthereumBlock")]
+#[allow(dead_code)]
+struct LightEthereumBlockSchema {
+    hash: Option<String>,
+    #[serde(rename = "parentHash")]
+    parent_hash: String,
+    number: Option<String>,
+    timestamp: String,
+    #[serde(rename = "logsBloom")]
+    logs_bloom: Option<String>,
+    #[serde(rename = "baseFeePerGas", default)]
+    base_fee_per_gas: Option<String>,
+    transactions: Vec<LightTransaction>,
+}
+
+/// The JSON schema for the wire format of `EthereumBlock`
+#[cfg(feature = "schema")]
+pub fn ethereum_block_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(EthereumBlockV2)
+}
+
 impl From<EthereumBlockV1> for EthereumBlockV2 {
     fn from(b: EthereumBlockV1) -> Self {
         Self {
@@ -293,80 +1724,4144 @@ impl From<EthereumBlockV1> for EthereumBlockV2 {
             transaction_receipts: b
                 .transaction_receipts
                 .into_iter()
-                .map(|arc_receipt| StoreTransactionReceipt::from((*arc_receipt).clone()))
-                .map(Arc::new).collect(),
+                .map(|arc_receipt| StoreTransactionReceipt::from(arc_receipt.as_ref()))
+                .map(Arc::new)
+                .collect(),
+        }
+    }
+}
+
+/// Statistics about the receipts seen while converting a V1 block
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConversionStats {
+    pub total_receipts: usize,
+    /// Receipts from light clients, which do not report `gas_used`
+    pub receipts_missing_gas_used: usize,
+    pub failed_txns: usize,
+}
+
+impl EthereumBlockV2 {
+    /// Converts a V1 block like `EthereumBlockV2::from`, and reports how
+    /// complete the receipts of the block were
+    pub fn from_v1_with_stats(b: EthereumBlockV1) -> (EthereumBlockV2, ConversionStats) {
+        let mut stats = ConversionStats::default();
+        for receipt in &b.transaction_receipts {
+            stats.total_receipts += 1;
+            if receipt.gas_used.is_none() {
+                stats.receipts_missing_gas_used += 1;
+            }
+            if !evaluate_transaction_status(receipt.status) {
+                stats.failed_txns += 1;
+            }
         }
+        (EthereumBlockV2::from(b), stats)
     }
 }
 
 pub type EthereumBlock = EthereumBlockV2;
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
-pub struct EthereumCall {
-    pub from: Address,
-    pub to: Address,
-    pub value: U256,
+/// The header data of a block together with its transaction count, for
+/// listing blocks without their transactions and logs. Number and hash are
+/// `None` for pending blocks
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSummary {
+    pub number: Option<BlockNumber>,
+    pub hash: Option<H256>,
+    pub parent_hash: H256,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub transaction_count: usize,
     pub gas_used: U256,
-    pub input: Bytes,
-    pub output: Bytes,
-    pub block_number: BlockNumber,
-    pub block_hash: H256,
-    pub transaction_hash: Option<H256>,
-    pub transaction_index: u64,
+    pub base_fee_per_gas: Option<U256>,
 }
 
-impl EthereumCall {
-    pub fn try_from_trace(trace: &Trace) -> Option<Self> {
-        // The parity-ethereum tracing api returns traces for operations which had execution errors.
-        // Filter errorful traces out, since call handlers should only run on successful CALLs.
-        if trace.error.is_some() {
-            return None;
+impl From<&EthereumBlock> for BlockSummary {
+    fn from(b: &EthereumBlock) -> Self {
+        let block = &b.block;
+        BlockSummary {
+            number: block.try_number().ok(),
+            hash: block.hash,
+            parent_hash: block.parent_hash,
+            timestamp: block.unix_timestamp(),
+            transaction_count: block.transaction_count(),
+            gas_used: block.gas_used,
+            base_fee_per_gas: block.base_fee_per_gas,
         }
-        // We are only interested in traces from CALLs
-        let call = match &trace.action {
-            // Contract to contract value transfers compile to the CALL opcode
-            // and have no input. Call handlers are for triggering on explicit method calls right now.
-            Action::Call(call) if call.input.0.len() >= 4 => call,
-            _ => return None,
-        };
-        let (output, gas_used) = match &trace.result {
-            Some(Res::Call(result)) => (result.output.clone(), result.gas_used),
-            _ => return None,
-        };
+    }
+}
 
-        // The only traces without transactions are those from Parity block reward contracts, we
-        // don't support triggering on that.
-        let transaction_index = trace.transaction_position? as u64;
+/// The transactions of a block keyed by their hash, for looking up the
+/// transaction of many logs. See `EthereumBlock::build_log_index`
+#[derive(Clone, Debug, Default)]
+pub struct LogIndex {
+    transactions: HashMap<H256, LightTransaction>,
+}
 
-        Some(EthereumCall {
-            from: call.from,
-            to: call.to,
-            value: call.value,
-            gas_used,
-            input: call.input.clone(),
-            output,
-            block_number: trace.block_number as BlockNumber,
-            block_hash: trace.block_hash,
-            transaction_hash: trace.transaction_hash,
-            transaction_index,
-        })
+impl LogIndex {
+    /// The transaction that emitted `log`; the same as
+    /// `LightEthereumBlockExt::transaction_for_log`, but without scanning
+    /// all transactions
+    pub fn transaction_for_log(&self, log: &Log) -> Option<&LightTransaction> {
+        log.transaction_hash
+            .and_then(|hash| self.transactions.get(&hash))
     }
 }
 
-impl From<EthereumBlock> for BlockPtr {
-    fn from(b: EthereumBlock) -> BlockPtr {
-        BlockPtr::from((b.block.hash.unwrap(), b.block.number.unwrap().as_u64()))
-    }
+/// A disagreement between the transactions and the receipts of a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiptMismatch {
+    /// A receipt for a transaction that is not in the block
+    OrphanReceipt(H256),
+    /// A transaction in the block without a receipt
+    MissingReceipt(H256),
 }
 
-impl<'a> From<&'a EthereumBlock> for BlockPtr {
-    fn from(b: &'a EthereumBlock) -> BlockPtr {
-        BlockPtr::from((b.block.hash.unwrap(), b.block.number.unwrap().as_u64()))
-    }
+/// Why a block body and separately fetched receipts can not be combined
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum MergeError {
+    #[error("block has no hash; it may be pending")]
+    PendingBlock,
+    #[error(
+        "receipt for transaction {transaction_hash:?} belongs to block {receipt_block_hash:?}, not {block_hash:?}"
+    )]
+    BlockHashMismatch {
+        transaction_hash: H256,
+        block_hash: H256,
+        receipt_block_hash: Option<H256>,
+    },
 }
 
-impl<'a> From<&'a EthereumCall> for BlockPtr {
-    fn from(call: &'a EthereumCall) -> BlockPtr {
-        BlockPtr::from((call.block_hash, call.block_number))
+impl EthereumBlockV2 {
+    /// Combine a block body with receipts that were fetched separately,
+    /// checking that every receipt belongs to the block. Receipts without a
+    /// block hash are rejected since we can not tell where they came from
+    pub fn with_receipts(
+        block: Arc<LightEthereumBlock>,
+        receipts: Vec<Arc<StoreTransactionReceipt>>,
+    ) -> Result<EthereumBlock, MergeError> {
+        let block_hash = block.hash.ok_or(MergeError::PendingBlock)?;
+        if let Some(receipt) = receipts
+            .iter()
+            .find(|receipt| receipt.block_hash != Some(block_hash))
+        {
+            return Err(MergeError::BlockHashMismatch {
+                transaction_hash: receipt.transaction_hash,
+                block_hash,
+                receipt_block_hash: receipt.block_hash,
+            });
+        }
+
+        Ok(EthereumBlock {
+            block,
+            transaction_receipts: receipts,
+        })
+    }
+
+    /// Build an index for looking up the transaction of a log.
+    /// `LightEthereumBlockExt::transaction_for_log` scans all transactions
+    /// for each log; callers that process many logs of a block should build
+    /// the index once and use `LogIndex::transaction_for_log` instead
+    pub fn build_log_index(&self) -> LogIndex {
+        let mut transactions = HashMap::with_capacity(self.block.transactions.len());
+        for tx in &self.block.transactions {
+            // Keep the first transaction for a hash, like the linear scan
+            transactions.entry(tx.hash).or_insert_with(|| tx.clone());
+        }
+        LogIndex { transactions }
+    }
+
+    /// The receipt for the transaction with the given hash
+    pub fn receipt_for_transaction(&self, hash: H256) -> Option<&Arc<StoreTransactionReceipt>> {
+        self.transaction_receipts
+            .iter()
+            .find(|receipt| receipt.transaction_hash == hash)
+    }
+
+    /// All receipts in this block keyed by their transaction hash. Build this
+    /// once when looking up receipts for many transactions rather than
+    /// calling `receipt_for_transaction` repeatedly.
+    pub fn receipts_by_hash(&self) -> HashMap<H256, Arc<StoreTransactionReceipt>> {
+        self.transaction_receipts
+            .iter()
+            .map(|receipt| (receipt.transaction_hash, receipt.cheap_clone()))
+            .collect()
+    }
+
+    /// The transactions of this block, each with its receipt. The receipt
+    /// is `None` if it is missing from the block
+    pub fn transactions_with_receipts(
+        &self,
+    ) -> impl Iterator<Item = (&LightTransaction, Option<&StoreTransactionReceipt>)> {
+        let receipts: HashMap<H256, &StoreTransactionReceipt> = self
+            .transaction_receipts
+            .iter()
+            .map(|receipt| (receipt.transaction_hash, receipt.as_ref()))
+            .collect();
+        self.block
+            .transactions
+            .iter()
+            .map(move |tx| (tx, receipts.get(&tx.hash).copied()))
+    }
+
+    /// The transaction in this block that produced `receipt`
+    pub fn transaction_for_receipt(
+        &self,
+        receipt: &StoreTransactionReceipt,
+    ) -> Option<&LightTransaction> {
+        self.block
+            .transactions
+            .iter()
+            .find(|tx| tx.hash == receipt.transaction_hash)
+    }
+
+    /// A fingerprint of this block made from the block hash, the block
+    /// number and the hashes of the receipts' transactions. Receipts are
+    /// sorted by transaction index first, so that the fingerprint does not
+    /// depend on the order in which receipts are stored
+    pub fn content_hash(&self) -> H256 {
+        let mut receipts: Vec<(U64, H256)> = self
+            .transaction_receipts
+            .iter()
+            .map(|receipt| (receipt.transaction_index, receipt.transaction_hash))
+            .collect();
+        receipts.sort();
+
+        let mut sponge = tiny_keccak::Keccak::new_keccak256();
+        sponge.update(self.block.hash.unwrap_or_default().as_bytes());
+        sponge.update(&self.block.number.unwrap_or_default().as_u64().to_be_bytes());
+        for (_, hash) in receipts {
+            sponge.update(hash.as_bytes());
+        }
+        let mut result = [0u8; 32];
+        sponge.finalize(&mut result);
+        H256::from(result)
+    }
+
+    /// Checks that there is exactly one receipt for each transaction in
+    /// the block, and reports all receipts and transactions that do not
+    /// match up
+    pub fn validate_receipts(&self) -> Result<(), Vec<ReceiptMismatch>> {
+        let tx_hashes: HashSet<H256> = self.block.transactions.iter().map(|tx| tx.hash).collect();
+        let receipt_hashes: HashSet<H256> = self
+            .transaction_receipts
+            .iter()
+            .map(|receipt| receipt.transaction_hash)
+            .collect();
+
+        let orphans = self
+            .transaction_receipts
+            .iter()
+            .filter(|receipt| !tx_hashes.contains(&receipt.transaction_hash))
+            .map(|receipt| ReceiptMismatch::OrphanReceipt(receipt.transaction_hash));
+        let missing = self
+            .block
+            .transactions
+            .iter()
+            .filter(|tx| !receipt_hashes.contains(&tx.hash))
+            .map(|tx| ReceiptMismatch::MissingReceipt(tx.hash));
+        let mismatches: Vec<_> = orphans.chain(missing).collect();
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+
+    /// Whether the receipts are stored in transaction order, with
+    /// transaction indexes `0, 1, 2, ...` without gaps
+    pub fn receipts_are_ordered(&self) -> bool {
+        self.transaction_receipts
+            .iter()
+            .enumerate()
+            .all(|(i, receipt)| receipt.transaction_index == U64::from(i))
+    }
+
+    /// The receipts sorted by transaction index. Some nodes return
+    /// receipts out of order; this lets callers rely on the order
+    pub fn sorted_receipts(&self) -> Vec<Arc<StoreTransactionReceipt>> {
+        let mut receipts = self.transaction_receipts.clone();
+        receipts.sort_by_key(|receipt| receipt.transaction_index);
+        receipts
+    }
+
+    /// A compact bincode encoding of this block for caching. The JSON
+    /// `Serialize` impl is not used for this; see the `binary` module
+    pub fn to_bytes(&self) -> Result<Vec<u8>, anyhow::Error> {
+        super::binary::encode(self)
+    }
+
+    /// Decodes a block encoded with `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        super::binary::decode(bytes)
+    }
+
+    /// The JSON encoding of this block with the keys of all objects sorted
+    /// and without whitespace, so that equal blocks always produce the same
+    /// string, e.g., for hashing
+    pub fn to_canonical_json(&self) -> Result<String, serde_json::Error> {
+        fn canonicalize(value: serde_json::Value) -> serde_json::Value {
+            match value {
+                serde_json::Value::Object(map) => {
+                    let mut entries: Vec<_> = map.into_iter().collect();
+                    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                    serde_json::Value::Object(
+                        entries
+                            .into_iter()
+                            .map(|(key, value)| (key, canonicalize(value)))
+                            .collect(),
+                    )
+                }
+                serde_json::Value::Array(values) => {
+                    serde_json::Value::Array(values.into_iter().map(canonicalize).collect())
+                }
+                value => value,
+            }
+        }
+
+        serde_json::to_string(&canonicalize(serde_json::to_value(self)?))
+    }
+
+    /// Serializes this block with its transactions replaced by their
+    /// hashes; see [`tx_hashes_only`] for the caveats
+    pub fn serialize_hashes_only<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        tx_hashes_only::serialize(self, serializer)
+    }
+
+    /// Deserializes a block written by `serialize_hashes_only`. The
+    /// transactions of the result only have their `hash` set
+    pub fn deserialize_hashes_only<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        tx_hashes_only::deserialize(deserializer)
+    }
+
+    /// The addresses of all contracts created in this block, in receipt
+    /// order
+    pub fn created_contracts(&self) -> Vec<Address> {
+        self.transaction_receipts
+            .iter()
+            .filter_map(|receipt| receipt.created_contract())
+            .collect()
+    }
+
+    /// The logs in this block whose event signature is `sig`
+    pub fn logs_for_topic0(&self, sig: H256) -> Vec<&Log> {
+        self.transaction_receipts
+            .iter()
+            .flat_map(|receipt| receipt.logs_for_topic0(sig))
+            .collect()
+    }
+
+    /// The logs in this block emitted by `address`, in the order in which
+    /// they appear in the block
+    pub fn logs_for_address(&self, address: Address) -> Vec<&Log> {
+        self.logs().filter(|log| log.address == address).collect()
+    }
+
+    /// The logs of each transaction in this block together with the
+    /// transaction's hash, ordered by transaction index. Transactions
+    /// without logs are included with an empty list
+    pub fn logs_by_transaction(&self) -> Vec<(H256, Vec<&Log>)> {
+        let mut receipts: Vec<_> = self.transaction_receipts.iter().collect();
+        receipts.sort_by_key(|receipt| receipt.transaction_index);
+        receipts
+            .into_iter()
+            .map(|receipt| (receipt.transaction_hash, receipt.logs.iter().collect()))
+            .collect()
+    }
+
+    /// The logs in this block emitted by `address` whose event signature is
+    /// `sig`
+    pub fn logs_for_address_and_topic0(&self, address: Address, sig: H256) -> Vec<&Log> {
+        self.logs()
+            .filter(|log| log.address == address && log.topics.first() == Some(&sig))
+            .collect()
+    }
+
+    /// This block without any receipts, for subgraphs that never look at
+    /// receipts
+    pub fn without_receipts(self) -> EthereumBlock {
+        EthereumBlock {
+            transaction_receipts: Vec::new(),
+            ..self
+        }
+    }
+
+    /// This block with the logs removed from all receipts
+    pub fn strip_logs(mut self) -> EthereumBlock {
+        for receipt in &mut self.transaction_receipts {
+            if !receipt.logs.is_empty() {
+                Arc::make_mut(receipt).logs = Vec::new();
+            }
+        }
+        self
+    }
+
+    /// Fills in `gas_used` for receipts that lack it, as light clients do,
+    /// from the difference between their `cumulative_gas_used` and that of
+    /// the preceding receipt. The receipts are sorted by transaction index
+    /// first. Gas is only filled in when the preceding transaction's receipt
+    /// is present, since the difference is meaningless otherwise
+    pub fn backfill_gas_used(&mut self) {
+        self.transaction_receipts
+            .sort_by_key(|receipt| receipt.transaction_index);
+
+        let mut previous: Option<(u64, U256)> = None;
+        for receipt in &mut self.transaction_receipts {
+            let index = receipt.transaction_index.as_u64();
+            if receipt.gas_used.is_none() {
+                let cumulative_before = match previous {
+                    None if index == 0 => Some(U256::zero()),
+                    Some((prev_index, cumulative)) if prev_index + 1 == index => Some(cumulative),
+                    _ => None,
+                };
+                if let Some(gas_used) = cumulative_before
+                    .and_then(|before| receipt.cumulative_gas_used.checked_sub(before))
+                {
+                    Arc::make_mut(receipt).gas_used = Some(gas_used);
+                }
+            }
+            previous = Some((index, receipt.cumulative_gas_used));
+        }
+    }
+
+    /// The distinct event signatures, i.e., first topics, of the logs in
+    /// this block. Logs without topics are skipped
+    pub fn distinct_topic0s(&self) -> HashSet<H256> {
+        self.logs()
+            .filter_map(|log| log.topics.first().copied())
+            .collect()
+    }
+
+    /// The distinct addresses that emitted logs in this block
+    pub fn distinct_log_addresses(&self) -> HashSet<Address> {
+        self.logs().map(|log| log.address).collect()
+    }
+
+    /// All logs in this block, in the order of the receipts
+    fn logs(&self) -> impl Iterator<Item = &Log> {
+        self.transaction_receipts
+            .iter()
+            .flat_map(|receipt| receipt.logs.iter())
+    }
+
+    /// The gas used by all transactions in this block. Receipts that do not
+    /// report `gas_used` are counted as zero. The total saturates at
+    /// `U256::MAX`
+    pub fn total_gas_used(&self) -> U256 {
+        self.transaction_receipts
+            .iter()
+            .filter_map(|receipt| receipt.gas_used)
+            .fold(U256::zero(), |total, gas| total.saturating_add(gas))
+    }
+
+    /// The gas used by the transactions in this block that deployed a
+    /// contract. Like `total_gas_used`, receipts without `gas_used` count as
+    /// zero and the total saturates at `U256::MAX`
+    pub fn deployment_gas(&self) -> U256 {
+        self.transaction_receipts
+            .iter()
+            .filter(|receipt| receipt.contract_address.is_some())
+            .filter_map(|receipt| receipt.gas_used)
+            .fold(U256::zero(), |total, gas| total.saturating_add(gas))
+    }
+
+    /// The fees paid by all transactions in this block, or `None` if the
+    /// effective gas price of any transaction is not known or the total
+    /// overflows
+    pub fn total_fees_paid(&self) -> Option<U256> {
+        self.transaction_receipts
+            .iter()
+            .try_fold(U256::zero(), |total, receipt| {
+                let price = receipt.effective_gas_price?;
+                total.checked_add(price.checked_mul(receipt.gas_used.unwrap_or_default())?)
+            })
+    }
+
+    /// The total size of the input of all transactions in this block
+    pub fn total_calldata_bytes(&self) -> usize {
+        self.block
+            .transactions
+            .iter()
+            .map(LightTransaction::input_size)
+            .sum()
+    }
+
+    /// The number of transactions in this block that failed
+    pub fn failed_transaction_count(&self) -> usize {
+        self.transaction_receipts
+            .iter()
+            .filter(|receipt| transaction_status(receipt.status) == TransactionStatus::Failed)
+            .count()
+    }
+
+    /// The given percentiles of the effective gas prices paid by the
+    /// transactions in this block, using the nearest-rank method. Receipts
+    /// without an effective gas price are skipped. The result has one entry
+    /// per requested percentile, which is `None` if no prices are known or
+    /// the percentile is above 100
+    pub fn fee_percentiles(&self, percentiles: &[u8]) -> Vec<Option<U256>> {
+        let mut prices: Vec<U256> = self
+            .transaction_receipts
+            .iter()
+            .filter_map(|receipt| receipt.effective_gas_price)
+            .collect();
+        prices.sort();
+
+        percentiles
+            .iter()
+            .map(|&percentile| {
+                if prices.is_empty() || percentile > 100 {
+                    return None;
+                }
+                let rank = (percentile as usize * prices.len() + 99) / 100;
+                Some(prices[rank.saturating_sub(1)])
+            })
+            .collect()
+    }
+
+    /// The fraction of transactions in this block that did not fail, or
+    /// `None` if the block has no transactions
+    pub fn success_rate(&self) -> Option<f64> {
+        let total = self.transaction_receipts.len();
+        if total == 0 {
+            return None;
+        }
+        let succeeded = total - self.failed_transaction_count();
+        Some(succeeded as f64 / total as f64)
+    }
+}
+
+/// The kind of call that produced a trace. Mirrors `web3::types::CallType`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CallType {
+    #[default]
+    None,
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+}
+
+impl From<&web3::types::CallType> for CallType {
+    fn from(call_type: &web3::types::CallType) -> Self {
+        match call_type {
+            web3::types::CallType::None => CallType::None,
+            web3::types::CallType::Call => CallType::Call,
+            web3::types::CallType::CallCode => CallType::CallCode,
+            web3::types::CallType::DelegateCall => CallType::DelegateCall,
+            web3::types::CallType::StaticCall => CallType::StaticCall,
+        }
+    }
+}
+
+/// Builds an `EthereumBlock` for tests. Anything that is not set explicitly
+/// gets a default value, so that `EthereumBlockBuilder::new().number(1).build()`
+/// produces a usable block
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Clone, Debug)]
+pub struct EthereumBlockBuilder {
+    block: LightEthereumBlock,
+    receipts: Vec<StoreTransactionReceipt>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl EthereumBlockBuilder {
+    pub fn new() -> Self {
+        Self {
+            block: LightEthereumBlock {
+                hash: Some(H256::zero()),
+                number: Some(U64::zero()),
+                logs_bloom: Some(H2048::zero()),
+                ..Default::default()
+            },
+            receipts: Vec::new(),
+        }
+    }
+
+    pub fn number(mut self, number: u64) -> Self {
+        self.block.number = Some(U64::from(number));
+        self
+    }
+
+    pub fn hash(mut self, hash: H256) -> Self {
+        self.block.hash = Some(hash);
+        self
+    }
+
+    pub fn parent_hash(mut self, parent_hash: H256) -> Self {
+        self.block.parent_hash = parent_hash;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u64) -> Self {
+        self.block.timestamp = U256::from(timestamp);
+        self
+    }
+
+    pub fn add_transaction(mut self, transaction: LightTransaction) -> Self {
+        self.block.transactions.push(transaction);
+        self
+    }
+
+    pub fn add_receipt(mut self, receipt: StoreTransactionReceipt) -> Self {
+        self.receipts.push(receipt);
+        self
+    }
+
+    pub fn build(self) -> EthereumBlock {
+        EthereumBlock {
+            block: Arc::new(self.block),
+            transaction_receipts: self.receipts.into_iter().map(Arc::new).collect(),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl Default for EthereumBlockBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EthereumCall {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub gas_used: U256,
+    /// The call's input, shared so that clones of a call do not copy the
+    /// calldata
+    pub input: Arc<Bytes>,
+    pub output: Arc<Bytes>,
+    pub block_number: BlockNumber,
+    pub block_hash: H256,
+    pub transaction_hash: Option<H256>,
+    pub transaction_index: u64,
+    pub call_type: CallType,
+    /// The depth of this call in the call tree; the top-level call of a
+    /// transaction has depth 0
+    pub depth: usize,
+    /// The path from the top-level call of the transaction to this call;
+    /// each entry is the index of a call among its siblings
+    pub trace_address: Vec<usize>,
+}
+
+impl fmt::Display for EthereumCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.call_type {
+            CallType::CallCode => "CALLCODE",
+            CallType::DelegateCall => "DELEGATECALL",
+            CallType::StaticCall => "STATICCALL",
+            CallType::Call | CallType::None => "CALL",
+        };
+        write!(f, "{} to {:?}", kind, self.to)?;
+        match self.function_selector() {
+            Some(selector) => write!(f, " selector 0x{}", hex::encode(selector))?,
+            None => write!(f, " (no selector)")?,
+        }
+        match self.transaction_hash {
+            Some(hash) => write!(f, " (tx {:?}#{})", hash, self.transaction_index),
+            None => write!(f, " (no tx)"),
+        }
+    }
+}
+
+/// The reason a trace was not turned into an `EthereumCall`
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum TraceRejection {
+    #[error("trace had an execution error")]
+    HadError,
+    #[error("trace is not a CALL")]
+    NotACall,
+    #[error("call input is shorter than a function selector")]
+    InputTooShort,
+    #[error("trace has no call result")]
+    NoResult,
+    #[error("trace has no transaction position")]
+    NoTransactionPosition,
+}
+
+/// A call frame as returned by geth's `debug_traceTransaction` with the
+/// `callTracer`. Frames are nested: `calls` holds the frames of the calls
+/// made during this one
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GethCallFrame {
+    /// The opcode that created the frame, e.g., `CALL` or `CREATE`
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: Address,
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub value: Option<U256>,
+    pub gas: U256,
+    #[serde(rename = "gasUsed")]
+    pub gas_used: U256,
+    pub input: Bytes,
+    #[serde(default)]
+    pub output: Option<Bytes>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub calls: Vec<GethCallFrame>,
+}
+
+impl EthereumCall {
+    pub fn try_from_trace(trace: &Trace) -> Option<Self> {
+        Self::try_from_trace_verbose(trace).ok().flatten()
+    }
+
+    /// The calls in the call tree of a transaction as traced by geth's
+    /// `callTracer`, flattened in the same order as parity traces. Frames
+    /// are filtered like `try_from_trace` filters traces: frames that are
+    /// not calls, that failed, or whose input has no function selector are
+    /// skipped, though the calls they made are not.
+    ///
+    /// Geth frames do not say where the transaction is in the block; the
+    /// `transaction_index` of the calls is 0 and needs to be set by the
+    /// caller if it is needed
+    pub fn from_geth_call_frame(
+        frame: &GethCallFrame,
+        block: &BlockPtr,
+        tx_hash: H256,
+    ) -> Vec<EthereumCall> {
+        fn flatten(
+            frame: &GethCallFrame,
+            trace_address: Vec<usize>,
+            block: &BlockPtr,
+            tx_hash: H256,
+            calls: &mut Vec<EthereumCall>,
+        ) {
+            let call_type = match frame.call_type.to_ascii_uppercase().as_str() {
+                "CALL" => Some(CallType::Call),
+                "CALLCODE" => Some(CallType::CallCode),
+                "DELEGATECALL" => Some(CallType::DelegateCall),
+                "STATICCALL" => Some(CallType::StaticCall),
+                _ => None,
+            };
+            if let (Some(call_type), Some(to), None) = (call_type, frame.to, &frame.error) {
+                if frame.input.0.len() >= 4 {
+                    calls.push(EthereumCall {
+                        from: frame.from,
+                        to,
+                        value: frame.value.unwrap_or_default(),
+                        gas_used: frame.gas_used,
+                        input: Arc::new(frame.input.clone()),
+                        output: Arc::new(frame.output.clone().unwrap_or_default()),
+                        block_number: block.number,
+                        block_hash: block.hash_as_h256(),
+                        transaction_hash: Some(tx_hash),
+                        transaction_index: 0,
+                        call_type,
+                        depth: trace_address.len(),
+                        trace_address: trace_address.clone(),
+                    });
+                }
+            }
+
+            for (index, child) in frame.calls.iter().enumerate() {
+                let mut child_address = trace_address.clone();
+                child_address.push(index);
+                flatten(child, child_address, block, tx_hash, calls);
+            }
+        }
+
+        let mut calls = Vec::new();
+        flatten(frame, vec![], block, tx_hash, &mut calls);
+        calls
+    }
+
+    /// The calls in a batch of traces, e.g., as returned by `trace_block`.
+    /// Traces that `try_from_trace` rejects are skipped
+    pub fn many_from_traces(traces: &[Trace]) -> Vec<EthereumCall> {
+        traces.iter().filter_map(Self::try_from_trace).collect()
+    }
+
+    /// Like `try_from_trace`, but reports why a trace was rejected so that
+    /// the reason can be logged.
+    pub fn try_from_trace_verbose(trace: &Trace) -> Result<Option<Self>, TraceRejection> {
+        // The parity-ethereum tracing api returns traces for operations which had execution errors.
+        // Filter errorful traces out, since call handlers should only run on successful CALLs.
+        if trace.error.is_some() {
+            return Err(TraceRejection::HadError);
+        }
+        // We are only interested in traces from CALLs
+        let call = match &trace.action {
+            // Contract to contract value transfers compile to the CALL opcode
+            // and have no input. Call handlers are for triggering on explicit method calls right now.
+            Action::Call(call) if call.input.0.len() >= 4 => call,
+            Action::Call(_) => return Err(TraceRejection::InputTooShort),
+            _ => return Err(TraceRejection::NotACall),
+        };
+        let (output, gas_used) = match &trace.result {
+            Some(Res::Call(result)) => (result.output.clone(), result.gas_used),
+            _ => return Err(TraceRejection::NoResult),
+        };
+
+        // The only traces without transactions are those from Parity block reward contracts, we
+        // don't support triggering on that.
+        let transaction_index = trace
+            .transaction_position
+            .ok_or(TraceRejection::NoTransactionPosition)? as u64;
+
+        Ok(Some(EthereumCall {
+            from: call.from,
+            to: call.to,
+            value: call.value,
+            gas_used,
+            input: Arc::new(call.input.clone()),
+            output: Arc::new(output),
+            block_number: trace.block_number as BlockNumber,
+            block_hash: trace.block_hash,
+            transaction_hash: trace.transaction_hash,
+            transaction_index,
+            call_type: CallType::from(&call.call_type),
+            depth: trace.trace_address.len(),
+            trace_address: trace.trace_address.clone(),
+        }))
+    }
+
+    /// The gas used by this call per byte of its input, or `None` if the
+    /// input is empty
+    pub fn gas_per_input_byte(&self) -> Option<f64> {
+        if self.input.0.is_empty() {
+            return None;
+        }
+        Some(u256_to_f64(self.gas_used) / self.input.0.len() as f64)
+    }
+
+    /// Whether this call transferred a nonzero amount of ETH
+    pub fn transfers_value(&self) -> bool {
+        !self.value.is_zero()
+    }
+
+    /// Whether this call can change state. `STATICCALL`s can not
+    pub fn is_state_changing(&self) -> bool {
+        self.call_type != CallType::StaticCall
+    }
+
+    /// The 4-byte function selector at the start of the input, or `None` if
+    /// the input is too short to contain one.
+    pub fn function_selector(&self) -> Option<[u8; 4]> {
+        function_selector(&self.input)
+    }
+
+    /// The ABI-encoded arguments of the call, i.e., the input after the
+    /// function selector. Empty if the input is too short to hold a selector
+    pub fn arg_data(&self) -> &[u8] {
+        self.input.0.get(4..).unwrap_or(&[])
+    }
+
+    /// Whether the arguments consist of whole 32-byte words, as every valid
+    /// ABI encoding does. Decoders can use this to reject truncated calldata
+    pub fn has_complete_words(&self) -> bool {
+        self.arg_data().len() % 32 == 0
+    }
+
+    /// Whether this is the top-level call of its transaction
+    pub fn is_root_call(&self) -> bool {
+        self.trace_address.is_empty()
+    }
+
+    /// The trace address of the call that made this call, or `None` for the
+    /// top-level call
+    pub fn parent_trace_address(&self) -> Option<Vec<usize>> {
+        self.trace_address
+            .split_last()
+            .map(|(_, parent)| parent.to_vec())
+    }
+
+    /// The key that call triggers are deduplicated by
+    pub fn trigger_key(&self) -> CallTriggerKey<'_> {
+        CallTriggerKey {
+            from: &self.from,
+            to: &self.to,
+            value: &self.value,
+            gas_used: &self.gas_used,
+            input: &self.input,
+            output: &self.output,
+            block_number: self.block_number,
+            block_hash: &self.block_hash,
+            transaction_hash: self.transaction_hash.as_ref(),
+            transaction_index: self.transaction_index,
+        }
+    }
+
+    /// Whether `self` and `other` describe the same logical call. Only the
+    /// fields that identify a call are compared; `gas_used` and `output`
+    /// can differ between trace providers for the same call and are
+    /// ignored. The derived `PartialEq` stays strict so that it can be
+    /// used to detect when two providers disagree.
+    pub fn same_call_as(&self, other: &EthereumCall) -> bool {
+        self.from == other.from
+            && self.to == other.to
+            && self.value == other.value
+            && self.input == other.input
+            && self.block_hash == other.block_hash
+            && self.transaction_hash == other.transaction_hash
+    }
+}
+
+/// The fields of an `EthereumCall` that identify it as a trigger. The
+/// position of the call in the call tree, i.e., `call_type`, `depth` and
+/// `trace_address`, is left out, so that two calls that only differ in
+/// where a trace provider placed them are handled once
+#[derive(Debug, PartialEq, Eq)]
+pub struct CallTriggerKey<'a> {
+    from: &'a Address,
+    to: &'a Address,
+    value: &'a U256,
+    gas_used: &'a U256,
+    input: &'a Bytes,
+    output: &'a Bytes,
+    block_number: BlockNumber,
+    block_hash: &'a H256,
+    transaction_hash: Option<&'a H256>,
+    transaction_index: u64,
+}
+
+/// A contract creation extracted from a `CREATE` trace
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EthereumCreate {
+    pub from: Address,
+    pub value: U256,
+    /// The init code that was run to create the contract
+    pub init: Bytes,
+    pub gas_used: U256,
+    /// The address of the created contract
+    pub address: Address,
+    pub block_number: BlockNumber,
+    pub block_hash: H256,
+    pub transaction_hash: Option<H256>,
+}
+
+impl EthereumCreate {
+    /// Extracts the contract creation from a successful `CREATE` trace;
+    /// returns `None` for any other trace
+    pub fn try_from_trace(trace: &Trace) -> Option<Self> {
+        if trace.error.is_some() {
+            return None;
+        }
+        let create = match &trace.action {
+            Action::Create(create) => create,
+            _ => return None,
+        };
+        let (address, gas_used) = match &trace.result {
+            Some(Res::Create(result)) => (result.address, result.gas_used),
+            _ => return None,
+        };
+
+        Some(EthereumCreate {
+            from: create.from,
+            value: create.value,
+            init: create.init.clone(),
+            gas_used,
+            address,
+            block_number: trace.block_number as BlockNumber,
+            block_hash: trace.block_hash,
+            transaction_hash: trace.transaction_hash,
+        })
+    }
+}
+
+/// A contract self-destruct extracted from a `SUICIDE` trace
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EthereumSelfDestruct {
+    /// The address of the contract that destroyed itself
+    pub address: Address,
+    /// The address that received the contract's balance
+    pub refund_address: Address,
+    pub balance: U256,
+    pub block_number: BlockNumber,
+    pub block_hash: H256,
+    pub transaction_hash: Option<H256>,
+}
+
+impl EthereumSelfDestruct {
+    /// Extracts the self-destruct from a successful `SUICIDE` trace;
+    /// returns `None` for any other trace
+    pub fn try_from_trace(trace: &Trace) -> Option<Self> {
+        if trace.error.is_some() {
+            return None;
+        }
+        let suicide = match &trace.action {
+            Action::Suicide(suicide) => suicide,
+            _ => return None,
+        };
+
+        Some(EthereumSelfDestruct {
+            address: suicide.address,
+            refund_address: suicide.refund_address,
+            balance: suicide.balance,
+            block_number: trace.block_number as BlockNumber,
+            block_hash: trace.block_hash,
+            transaction_hash: trace.transaction_hash,
+        })
+    }
+}
+
+/// A plain transfer of ETH extracted from a `CALL` trace that moved value
+/// without calling a function. `EthereumCall::try_from_trace` rejects such
+/// traces since their input has no function selector
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EthereumTransfer {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub block_number: BlockNumber,
+    pub block_hash: H256,
+    pub transaction_hash: Option<H256>,
+    pub trace_address: Vec<usize>,
+}
+
+impl EthereumTransfer {
+    /// Extracts the transfer from a successful `CALL` trace with a nonzero
+    /// value and an input that is too short to hold a function selector;
+    /// returns `None` for any other trace
+    pub fn try_from_trace(trace: &Trace) -> Option<Self> {
+        if trace.error.is_some() {
+            return None;
+        }
+        let call = match &trace.action {
+            Action::Call(call) if call.input.0.len() < 4 && !call.value.is_zero() => call,
+            _ => return None,
+        };
+
+        Some(EthereumTransfer {
+            from: call.from,
+            to: call.to,
+            value: call.value,
+            block_number: trace.block_number as BlockNumber,
+            block_hash: trace.block_hash,
+            transaction_hash: trace.transaction_hash,
+            trace_address: trace.trace_address.clone(),
+        })
+    }
+}
+
+/// A trace classified by the kind of action it records
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEntry {
+    Call(EthereumCall),
+    Create(EthereumCreate),
+    SelfDestruct(EthereumSelfDestruct),
+}
+
+impl TraceEntry {
+    /// Parses `trace` with the parser for its action. Returns `None` for
+    /// traces with errors, for rewards, and for traces the parser rejects
+    pub fn from_trace(trace: &Trace) -> Option<TraceEntry> {
+        if trace.error.is_some() {
+            return None;
+        }
+        match &trace.action {
+            Action::Call(_) => EthereumCall::try_from_trace(trace).map(TraceEntry::Call),
+            Action::Create(_) => EthereumCreate::try_from_trace(trace).map(TraceEntry::Create),
+            Action::Suicide(_) => {
+                EthereumSelfDestruct::try_from_trace(trace).map(TraceEntry::SelfDestruct)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Splits a batch of traces into calls, contract creations and self
+/// destructs. Traces that `TraceEntry::from_trace` rejects are skipped
+pub fn partition_traces(
+    traces: &[Trace],
+) -> (
+    Vec<EthereumCall>,
+    Vec<EthereumCreate>,
+    Vec<EthereumSelfDestruct>,
+) {
+    let mut calls = Vec::new();
+    let mut creates = Vec::new();
+    let mut self_destructs = Vec::new();
+    for entry in traces.iter().filter_map(TraceEntry::from_trace) {
+        match entry {
+            TraceEntry::Call(call) => calls.push(call),
+            TraceEntry::Create(create) => creates.push(create),
+            TraceEntry::SelfDestruct(destruct) => self_destructs.push(destruct),
+        }
+    }
+    (calls, creates, self_destructs)
+}
+
+fn function_selector(input: &Bytes) -> Option<[u8; 4]> {
+    input.0.get(..4).map(|sel| [sel[0], sel[1], sel[2], sel[3]])
+}
+
+impl From<EthereumBlock> for BlockPtr {
+    fn from(b: EthereumBlock) -> BlockPtr {
+        b.block.block_ptr()
+    }
+}
+
+impl<'a> From<&'a EthereumBlock> for BlockPtr {
+    fn from(b: &'a EthereumBlock) -> BlockPtr {
+        b.block.block_ptr()
+    }
+}
+
+impl From<EthereumCall> for BlockPtr {
+    fn from(call: EthereumCall) -> BlockPtr {
+        BlockPtr::from((call.block_hash, call.block_number))
+    }
+}
+
+impl<'a> From<&'a EthereumCall> for BlockPtr {
+    fn from(call: &'a EthereumCall) -> BlockPtr {
+        BlockPtr::from((call.block_hash, call.block_number))
+    }
+}
+
+/// Access to the block of a raw trace, e.g., to bucket traces by block while
+/// streaming them and before converting them to `EthereumCall`s
+pub trait TraceBlockPtr {
+    /// The pointer to the block containing this trace, or `None` if the
+    /// block number does not fit into a `BlockNumber`
+    fn block_ptr(&self) -> Option<BlockPtr>;
+}
+
+impl TraceBlockPtr for Trace {
+    fn block_ptr(&self) -> Option<BlockPtr> {
+        let number = BlockNumber::try_from(self.block_number).ok()?;
+        Some(BlockPtr::from((self.block_hash, number)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Not all web3 types implement `Default`, so we build them by hand
+    fn web3_transaction() -> Transaction {
+        Transaction {
+            hash: H256::zero(),
+            nonce: U256::zero(),
+            block_hash: None,
+            block_number: None,
+            transaction_index: None,
+            from: None,
+            to: None,
+            value: U256::zero(),
+            gas_price: None,
+            gas: U256::zero(),
+            input: Bytes(vec![]),
+            v: None,
+            r: None,
+            s: None,
+            raw: None,
+            transaction_type: None,
+            access_list: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+        }
+    }
+
+    fn web3_receipt() -> TransactionReceipt {
+        TransactionReceipt {
+            transaction_hash: H256::zero(),
+            transaction_index: U64::zero(),
+            block_hash: None,
+            block_number: None,
+            from: Address::zero(),
+            to: None,
+            cumulative_gas_used: U256::zero(),
+            gas_used: None,
+            contract_address: None,
+            logs: vec![],
+            status: None,
+            root: None,
+            logs_bloom: H2048::zero(),
+            transaction_type: None,
+            effective_gas_price: None,
+        }
+    }
+
+    #[test]
+    fn light_transaction_type() {
+        let legacy = LightTransaction::default();
+        assert!(legacy.is_legacy());
+
+        let tx = Transaction {
+            transaction_type: Some(U64::from(2)),
+            ..web3_transaction()
+        };
+        let tx = LightTransaction::from(&tx);
+        assert_eq!(Some(U64::from(2)), tx.transaction_type);
+        assert!(!tx.is_legacy());
+
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["type"], "0x2");
+        let decoded: LightTransaction = serde_json::from_value(json).unwrap();
+        assert_eq!(tx, decoded);
+    }
+
+    #[test]
+    fn light_transaction_without_type_deserializes() {
+        let json = serde_json::to_value(LightTransaction::default()).unwrap();
+        assert!(json.get("type").is_none());
+
+        let tx: LightTransaction = serde_json::from_value(json).unwrap();
+        assert_eq!(None, tx.transaction_type);
+        assert!(tx.is_legacy());
+    }
+
+    #[test]
+    fn effective_gas_price() {
+        let legacy = LightTransaction {
+            gas_price: Some(U256::from(50)),
+            ..Default::default()
+        };
+        assert_eq!(U256::from(50), legacy.effective_gas_price(U256::from(10)));
+
+        // The base fee plus the tip exceeds the max fee, so the max fee caps
+        // the price that is actually paid
+        let tx = LightTransaction {
+            transaction_type: Some(U64::from(2)),
+            max_fee_per_gas: Some(U256::from(100)),
+            max_priority_fee_per_gas: Some(U256::from(20)),
+            ..Default::default()
+        };
+        assert_eq!(U256::from(100), tx.effective_gas_price(U256::from(90)));
+        assert_eq!(U256::from(70), tx.effective_gas_price(U256::from(50)));
+    }
+
+    #[test]
+    fn access_list() {
+        use web3::types::AccessListItem;
+
+        let a = Address::from_low_u64_be(1);
+        let b = Address::from_low_u64_be(2);
+        let tx = LightTransaction {
+            access_list: Some(vec![
+                AccessListItem {
+                    address: a,
+                    storage_keys: vec![H256::from_low_u64_be(1)],
+                },
+                AccessListItem {
+                    address: b,
+                    storage_keys: vec![],
+                },
+                AccessListItem {
+                    address: a,
+                    storage_keys: vec![H256::from_low_u64_be(2)],
+                },
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(vec![a, b], tx.accessed_addresses());
+        assert!(LightTransaction::default().accessed_addresses().is_empty());
+
+        let json = serde_json::to_value(&tx).unwrap();
+        assert!(json.get("accessList").is_some());
+        let decoded: LightTransaction = serde_json::from_value(json).unwrap();
+        assert_eq!(tx, decoded);
+
+        // Transactions stored before the access list was kept
+        let json = serde_json::to_value(LightTransaction::default()).unwrap();
+        assert!(json.get("accessList").is_none());
+        let decoded: LightTransaction = serde_json::from_value(json).unwrap();
+        assert_eq!(None, decoded.access_list);
+    }
+
+    #[test]
+    fn try_number() {
+        let block = LightEthereumBlock {
+            number: Some(U64::from(42)),
+            ..Default::default()
+        };
+        assert_eq!(42, block.try_number().unwrap());
+        assert_eq!(42, block.number());
+
+        let pending = LightEthereumBlock::default();
+        let err = pending.try_number().unwrap_err();
+        assert!(err.to_string().contains("pending"));
+
+        let too_large = LightEthereumBlock {
+            number: Some(U64::from(i32::MAX as u64 + 1)),
+            ..Default::default()
+        };
+        assert_eq!(
+            Err(EthereumTypeError::BlockNumberOutOfRange(U64::from(
+                i32::MAX as u64 + 1
+            ))),
+            too_large.try_number()
+        );
+    }
+
+    #[test]
+    fn number_or() {
+        let block = EthereumBlockBuilder::new().number(7).build().block;
+        assert_eq!(7, block.number_or(-1));
+
+        let pending = LightEthereumBlock::default();
+        assert_eq!(-1, pending.number_or(-1));
+
+        let overflowing = LightEthereumBlock {
+            number: Some(U64::from(u64::MAX)),
+            ..Default::default()
+        };
+        assert_eq!(-1, overflowing.number_or(-1));
+    }
+
+    #[test]
+    fn try_block_ptr() {
+        let hash = H256::from_low_u64_be(7);
+        let block = LightEthereumBlock {
+            hash: Some(hash),
+            number: Some(U64::from(7)),
+            ..Default::default()
+        };
+        assert_eq!(Some(BlockPtr::from((hash, 7u64))), block.try_block_ptr());
+        assert_eq!(block.block_ptr(), block.try_block_ptr().unwrap());
+
+        let pending = LightEthereumBlock {
+            hash: None,
+            number: None,
+            ..Default::default()
+        };
+        assert_eq!(None, pending.try_block_ptr());
+
+        let no_hash = LightEthereumBlock {
+            number: Some(U64::from(7)),
+            ..Default::default()
+        };
+        assert_eq!(None, no_hash.try_block_ptr());
+    }
+
+    fn call_trace(input: Vec<u8>) -> Trace {
+        use web3::types::{ActionType, Call, CallResult, CallType};
+
+        Trace {
+            action: Action::Call(Call {
+                from: Address::from_low_u64_be(1),
+                to: Address::from_low_u64_be(2),
+                value: U256::zero(),
+                gas: U256::from(100_000),
+                input: Bytes(input),
+                call_type: CallType::Call,
+            }),
+            result: Some(Res::Call(CallResult {
+                gas_used: U256::from(21_000),
+                output: Bytes(vec![]),
+            })),
+            trace_address: vec![],
+            subtraces: 0,
+            transaction_position: Some(0),
+            transaction_hash: Some(H256::from_low_u64_be(1)),
+            block_number: 1,
+            block_hash: H256::from_low_u64_be(1),
+            action_type: ActionType::Call,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn trace_rejection() {
+        let trace = call_trace(vec![1, 2, 3, 4]);
+        let call = EthereumCall::try_from_trace_verbose(&trace)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(call), EthereumCall::try_from_trace(&trace));
+
+        let mut errored = call_trace(vec![1, 2, 3, 4]);
+        errored.error = Some("Reverted".to_string());
+        assert_eq!(
+            Err(TraceRejection::HadError),
+            EthereumCall::try_from_trace_verbose(&errored)
+        );
+
+        let short = call_trace(vec![1, 2, 3]);
+        assert_eq!(
+            Err(TraceRejection::InputTooShort),
+            EthereumCall::try_from_trace_verbose(&short)
+        );
+        assert_eq!(None, EthereumCall::try_from_trace(&short));
+
+        let mut no_result = call_trace(vec![1, 2, 3, 4]);
+        no_result.result = None;
+        assert_eq!(
+            Err(TraceRejection::NoResult),
+            EthereumCall::try_from_trace_verbose(&no_result)
+        );
+
+        let mut reward = call_trace(vec![1, 2, 3, 4]);
+        reward.transaction_position = None;
+        assert_eq!(
+            Err(TraceRejection::NoTransactionPosition),
+            EthereumCall::try_from_trace_verbose(&reward)
+        );
+    }
+
+    #[test]
+    fn call_type_and_depth() {
+        use web3::types::CallType as Web3CallType;
+
+        let mut trace = call_trace(vec![1, 2, 3, 4]);
+        trace.trace_address = vec![0, 1];
+        let call = EthereumCall::try_from_trace(&trace).unwrap();
+        assert_eq!(CallType::Call, call.call_type);
+        assert_eq!(2, call.depth);
+        assert!(call.is_state_changing());
+
+        if let Action::Call(call) = &mut trace.action {
+            call.call_type = Web3CallType::StaticCall;
+        }
+        let call = EthereumCall::try_from_trace(&trace).unwrap();
+        assert_eq!(CallType::StaticCall, call.call_type);
+        assert!(!call.is_state_changing());
+    }
+
+    #[test]
+    fn function_selector() {
+        let call = EthereumCall {
+            input: Arc::new(Bytes(vec![0xa9, 0x05, 0x9c, 0xbb, 0x01])),
+            ..Default::default()
+        };
+        assert_eq!(Some([0xa9, 0x05, 0x9c, 0xbb]), call.function_selector());
+
+        let short = EthereumCall {
+            input: Arc::new(Bytes(vec![0xa9, 0x05, 0x9c])),
+            ..Default::default()
+        };
+        assert_eq!(None, short.function_selector());
+
+        let empty = LightTransaction::default();
+        assert_eq!(None, empty.function_selector());
+
+        let tx = LightTransaction {
+            input: Bytes(vec![0xa9, 0x05, 0x9c, 0xbb]),
+            ..Default::default()
+        };
+        assert_eq!(Some([0xa9, 0x05, 0x9c, 0xbb]), tx.function_selector());
+    }
+
+    fn receipt(hash: u64, status: u64) -> Arc<StoreTransactionReceipt> {
+        Arc::new(StoreTransactionReceipt {
+            transaction_hash: H256::from_low_u64_be(hash),
+            transaction_index: U64::from(hash),
+            status: Some(U64::from(status)),
+            ..Default::default()
+        })
+    }
+
+    fn call_in(tx: u64) -> EthereumCall {
+        EthereumCall {
+            transaction_hash: Some(H256::from_low_u64_be(tx)),
+            input: Arc::new(Bytes(vec![tx as u8; 4])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn successful_calls() {
+        let block = EthereumBlockWithCalls {
+            ethereum_block: EthereumBlock {
+                block: Arc::new(LightEthereumBlock::default()),
+                transaction_receipts: vec![receipt(1, 1), receipt(2, 0)],
+            },
+            calls: Some(vec![call_in(1), call_in(2), call_in(1)]),
+        };
+
+        let calls: Vec<_> = block.successful_calls().collect();
+        assert_eq!(vec![&call_in(1), &call_in(1)], calls);
+    }
+
+    #[test]
+    fn partition_calls() {
+        let mut block = EthereumBlockWithCalls {
+            ethereum_block: EthereumBlock {
+                block: Arc::new(LightEthereumBlock::default()),
+                transaction_receipts: vec![receipt(1, 1), receipt(2, 0)],
+            },
+            calls: Some(vec![call_in(1), call_in(2), call_in(1)]),
+        };
+
+        let (succeeded, failed) = block.partition_calls().unwrap();
+        assert_eq!(vec![&call_in(1), &call_in(1)], succeeded);
+        assert_eq!(vec![&call_in(2)], failed);
+
+        block.calls = Some(vec![call_in(1), call_in(3)]);
+        assert_eq!(
+            EthereumTypeError::NoReceiptForTransaction(H256::from_low_u64_be(3)),
+            block.partition_calls().unwrap_err()
+        );
+
+        block.calls = Some(vec![EthereumCall::default()]);
+        assert_eq!(
+            EthereumTypeError::NoTransactionForCall,
+            block.partition_calls().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn transaction_for_call_succeeded() {
+        let block = EthereumBlockWithCalls {
+            ethereum_block: EthereumBlock {
+                block: Arc::new(LightEthereumBlock::default()),
+                transaction_receipts: vec![receipt(1, 1), receipt(2, 0)],
+            },
+            calls: None,
+        };
+
+        assert_eq!(Ok(true), block.transaction_for_call_succeeded(&call_in(1)));
+        assert_eq!(Ok(false), block.transaction_for_call_succeeded(&call_in(2)));
+        assert_eq!(
+            Err(EthereumTypeError::NoReceiptForTransaction(
+                H256::from_low_u64_be(3)
+            )),
+            block.transaction_for_call_succeeded(&call_in(3))
+        );
+        assert_eq!(
+            Err(EthereumTypeError::NoTransactionForCall),
+            block.transaction_for_call_succeeded(&EthereumCall::default())
+        );
+
+        // `try_number` reports a pending block with a typed error, too
+        assert_eq!(
+            Err(EthereumTypeError::MissingBlockNumber),
+            LightEthereumBlock::default().try_number()
+        );
+    }
+
+    #[test]
+    fn call_gas() {
+        let small = EthereumCall {
+            gas_used: U256::from(400),
+            input: Arc::new(Bytes(vec![1; 4])),
+            ..Default::default()
+        };
+        let large = EthereumCall {
+            gas_used: U256::from(1000),
+            input: Arc::new(Bytes(vec![1; 100])),
+            ..Default::default()
+        };
+        assert_eq!(Some(100.0), small.gas_per_input_byte());
+        assert_eq!(Some(10.0), large.gas_per_input_byte());
+        assert_eq!(None, EthereumCall::default().gas_per_input_byte());
+
+        let block = EthereumBlockWithCalls {
+            ethereum_block: EthereumBlock::default(),
+            calls: Some(vec![small, large]),
+        };
+        assert_eq!(U256::from(1400), block.total_call_gas());
+    }
+
+    #[test]
+    fn calls_to_and_from() {
+        let call = |from: u64, to: u64| EthereumCall {
+            from: Address::from_low_u64_be(from),
+            to: Address::from_low_u64_be(to),
+            ..Default::default()
+        };
+        let block = EthereumBlockWithCalls {
+            ethereum_block: EthereumBlock::default(),
+            calls: Some(vec![call(1, 2), call(2, 3), call(1, 3), call(3, 1)]),
+        };
+        let addr = Address::from_low_u64_be;
+
+        let to: Vec<_> = block.calls_to(addr(3)).collect();
+        assert_eq!(vec![&call(2, 3), &call(1, 3)], to);
+        let from: Vec<_> = block.calls_from(addr(1)).collect();
+        assert_eq!(vec![&call(1, 2), &call(1, 3)], from);
+        assert_eq!(0, block.calls_to(addr(4)).count());
+
+        let no_calls = EthereumBlockWithCalls {
+            calls: None,
+            ..block
+        };
+        assert_eq!(0, no_calls.calls_from(addr(1)).count());
+    }
+
+    #[test]
+    fn value_transferring_calls() {
+        let paying = EthereumCall {
+            value: U256::from(10),
+            ..call_in(1)
+        };
+        let block = EthereumBlockWithCalls {
+            ethereum_block: EthereumBlock::default(),
+            calls: Some(vec![call_in(1), paying.clone()]),
+        };
+
+        assert!(!call_in(1).transfers_value());
+        assert!(paying.transfers_value());
+        let calls: Vec<_> = block.value_transferring_calls().collect();
+        assert_eq!(vec![&paying], calls);
+
+        let no_calls = EthereumBlockWithCalls {
+            calls: None,
+            ..block
+        };
+        assert_eq!(0, no_calls.value_transferring_calls().count());
+    }
+
+    #[test]
+    fn fee_percentiles() {
+        let block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![
+                receipt_with_gas(0, Some(21_000), Some(30)),
+                receipt_with_gas(1, Some(21_000), Some(10)),
+                receipt_with_gas(2, Some(21_000), Some(50)),
+                receipt_with_gas(3, Some(21_000), Some(20)),
+                receipt_with_gas(4, Some(21_000), Some(40)),
+                receipt_with_gas(5, Some(21_000), None),
+            ],
+        };
+        assert_eq!(
+            vec![
+                Some(U256::from(10)),
+                Some(U256::from(20)),
+                Some(U256::from(30)),
+                Some(U256::from(50)),
+                None
+            ],
+            block.fee_percentiles(&[0, 25, 50, 100, 101])
+        );
+
+        let empty = EthereumBlock::default();
+        assert_eq!(vec![None, None], empty.fee_percentiles(&[50, 90]));
+    }
+
+    #[test]
+    fn success_rate() {
+        let block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![
+                receipt(1, 1),
+                receipt(2, 0),
+                receipt(3, 1),
+                receipt(4, 0),
+                receipt(5, 1),
+            ],
+        };
+        assert_eq!(2, block.failed_transaction_count());
+        assert_eq!(Some(0.6), block.success_rate());
+
+        assert_eq!(None, EthereumBlock::default().success_rate());
+    }
+
+    #[test]
+    fn block_summary() {
+        let mut block = EthereumBlockBuilder::new()
+            .number(9)
+            .hash(H256::from_low_u64_be(0xa9))
+            .parent_hash(H256::from_low_u64_be(0xa8))
+            .timestamp(1_600_000_000)
+            .add_transaction(LightTransaction::default())
+            .add_transaction(LightTransaction::default())
+            .build();
+        let light = Arc::make_mut(&mut block.block);
+        light.gas_used = U256::from(42_000);
+        light.base_fee_per_gas = Some(U256::from(7));
+
+        assert_eq!(
+            BlockSummary {
+                number: Some(9),
+                hash: Some(H256::from_low_u64_be(0xa9)),
+                parent_hash: H256::from_low_u64_be(0xa8),
+                timestamp: 1_600_000_000,
+                transaction_count: 2,
+                gas_used: U256::from(42_000),
+                base_fee_per_gas: Some(U256::from(7)),
+            },
+            BlockSummary::from(&block)
+        );
+
+        let pending = BlockSummary::from(&EthereumBlock::default());
+        assert_eq!(None, pending.number);
+        assert_eq!(None, pending.hash);
+    }
+
+    #[test]
+    fn transactions_with_receipts() {
+        let tx = |hash: u64| LightTransaction {
+            hash: H256::from_low_u64_be(hash),
+            ..Default::default()
+        };
+        let block = EthereumBlockBuilder::new()
+            .add_transaction(tx(1))
+            .add_transaction(tx(2))
+            .add_transaction(tx(3))
+            .add_receipt((*receipt(3, 1)).clone())
+            .add_receipt((*receipt(1, 0)).clone())
+            .build();
+
+        let pairs: Vec<_> = block.transactions_with_receipts().collect();
+        assert_eq!(
+            vec![
+                (&tx(1), Some(receipt(1, 0).as_ref())),
+                (&tx(2), None),
+                (&tx(3), Some(receipt(3, 1).as_ref())),
+            ],
+            pairs
+        );
+    }
+
+    #[test]
+    fn log_index() {
+        let tx = |hash: u64| LightTransaction {
+            hash: H256::from_low_u64_be(hash),
+            nonce: U256::from(hash),
+            ..Default::default()
+        };
+        let mut builder = EthereumBlockBuilder::new();
+        for hash in 1..=100 {
+            builder = builder.add_transaction(tx(hash));
+        }
+        let block = builder.build();
+        let index = block.build_log_index();
+
+        let logs = (0..=101).map(|hash| Log {
+            transaction_hash: (hash > 0).then(|| H256::from_low_u64_be(hash)),
+            ..log(1, &[])
+        });
+        for log in logs {
+            assert_eq!(
+                block.block.transaction_for_log(&log).as_ref(),
+                index.transaction_for_log(&log)
+            );
+        }
+        assert_eq!(
+            Some(&tx(42)),
+            index.transaction_for_log(&Log {
+                transaction_hash: Some(H256::from_low_u64_be(42)),
+                ..log(1, &[])
+            })
+        );
+    }
+
+    #[test]
+    fn receipt_for_transaction() {
+        let block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![receipt(1, 1), receipt(2, 0)],
+        };
+
+        let found = block.receipt_for_transaction(H256::from_low_u64_be(2));
+        assert_eq!(Some(&receipt(2, 0)), found);
+        assert_eq!(
+            None,
+            block.receipt_for_transaction(H256::from_low_u64_be(3))
+        );
+
+        let by_hash = block.receipts_by_hash();
+        assert_eq!(2, by_hash.len());
+        assert_eq!(receipt(1, 1), by_hash[&H256::from_low_u64_be(1)]);
+    }
+
+    #[test]
+    fn display_block() {
+        let block = LightEthereumBlock {
+            number: Some(U64::from(12)),
+            hash: Some(H256::from_low_u64_be(0xab)),
+            ..Default::default()
+        };
+        assert_eq!(
+            "#12 (00000000000000000000000000000000000000000000000000000000000000ab)",
+            block.display().to_string()
+        );
+        assert_eq!(block.format(), format!("{}", block.display()));
+
+        let pending = LightEthereumBlock::default();
+        assert_eq!("none (-)", pending.display().to_string());
+        assert_eq!(pending.format(), pending.display().to_string());
+    }
+
+    #[test]
+    fn block_age() {
+        let block = LightEthereumBlock {
+            timestamp: U256::from(1_000),
+            ..Default::default()
+        };
+        assert_eq!(1_000, block.try_timestamp().unwrap().as_secs_since_epoch());
+        assert_eq!(
+            Some(chrono::Duration::seconds(600)),
+            block.age(BlockTime::since_epoch(1_600, 0))
+        );
+        assert_eq!(
+            Some(chrono::Duration::zero()),
+            block.age(BlockTime::since_epoch(1_000, 0))
+        );
+        // The block is from the future
+        assert_eq!(None, block.age(BlockTime::since_epoch(999, 0)));
+
+        let bad = LightEthereumBlock {
+            timestamp: U256::from(u64::MAX),
+            ..Default::default()
+        };
+        assert!(bad.try_timestamp().is_err());
+        assert_eq!(BlockTime::MAX, bad.timestamp());
+    }
+
+    #[test]
+    fn blob_transaction_round_trip() {
+        let tx = LightTransaction {
+            transaction_type: Some(U64::from(3)),
+            max_fee_per_gas: Some(U256::from(100)),
+            max_priority_fee_per_gas: Some(U256::from(2)),
+            max_fee_per_blob_gas: Some(U256::from(7)),
+            blob_versioned_hashes: Some(vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)]),
+            ..Default::default()
+        };
+        assert!(tx.is_blob_tx());
+        assert!(!LightTransaction::default().is_blob_tx());
+
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["maxFeePerBlobGas"], "0x7");
+        assert_eq!(2, json["blobVersionedHashes"].as_array().unwrap().len());
+        let decoded: LightTransaction = serde_json::from_value(json).unwrap();
+        assert_eq!(tx, decoded);
+    }
+
+    #[test]
+    fn transaction_status() {
+        use super::transaction_status;
+
+        assert_eq!(
+            TransactionStatus::Succeeded,
+            transaction_status(Some(U64::from(1)))
+        );
+        assert_eq!(
+            TransactionStatus::Failed,
+            transaction_status(Some(U64::from(0)))
+        );
+        assert_eq!(TransactionStatus::Unknown, transaction_status(None));
+
+        assert!(evaluate_transaction_status(Some(U64::from(1))));
+        assert!(!evaluate_transaction_status(Some(U64::from(0))));
+        assert!(evaluate_transaction_status(None));
+    }
+
+    #[test]
+    fn receipt_succeeded() {
+        let failed = receipt(1, 0);
+        assert!(failed.failed());
+        assert!(!failed.succeeded());
+
+        let succeeded = StoreTransactionReceipt {
+            gas_used: Some(U256::from(21_000)),
+            ..(*receipt(2, 1)).clone()
+        };
+        assert!(succeeded.succeeded());
+        assert!(!succeeded.failed());
+        assert!(!succeeded.is_light_client_receipt());
+
+        let light = StoreTransactionReceipt {
+            gas_used: None,
+            status: None,
+            ..Default::default()
+        };
+        assert!(light.is_light_client_receipt());
+        assert!(light.succeeded());
+    }
+
+    #[test]
+    fn computed_logs_bloom() {
+        let mut receipt = StoreTransactionReceipt {
+            logs: vec![log(1, &[0xa])],
+            ..Default::default()
+        };
+
+        let mut expected = H2048::zero();
+        for (byte, mask) in [
+            (52, 0x04),
+            (57, 0x22),
+            (114, 0x01),
+            (136, 0x01),
+            (239, 0x01),
+        ] {
+            expected.0[byte] = mask;
+        }
+        assert_eq!(expected, receipt.computed_logs_bloom());
+
+        assert!(!receipt.bloom_is_consistent());
+        receipt.logs_bloom = expected;
+        assert!(receipt.bloom_is_consistent());
+        receipt.logs_bloom.0[0] = 0xff;
+        assert!(receipt.bloom_is_consistent());
+        receipt.logs_bloom.0[57] = 0x20;
+        assert!(!receipt.bloom_is_consistent());
+    }
+
+    #[test]
+    fn receipt_without_bloom_and_root() {
+        let json = serde_json::json!({
+            "transactionHash": H256::from_low_u64_be(1),
+            "transactionIndex": "0x0",
+            "blockHash": H256::from_low_u64_be(2),
+            "blockNumber": "0x10",
+            "cumulativeGasUsed": "0x5208",
+            "gasUsed": "0x5208",
+            "contractAddress": null,
+            "logs": [],
+            "status": "0x1",
+        });
+
+        let receipt: StoreTransactionReceipt = serde_json::from_value(json).unwrap();
+        assert_eq!(H2048::zero(), receipt.logs_bloom);
+        assert_eq!(None, receipt.root);
+        assert_eq!(Some(U64::from(1)), receipt.status);
+    }
+
+    #[test]
+    fn receipt_accepts_snake_case() {
+        let bloom = format!("0x{}", "00".repeat(256));
+        let json = serde_json::json!({
+            "transaction_hash": H256::from_low_u64_be(1),
+            "transactionIndex": "0x2",
+            "block_hash": H256::from_low_u64_be(3),
+            "blockNumber": "0x4",
+            "cumulative_gas_used": "0x5208",
+            "gas_used": "0x5208",
+            "contractAddress": null,
+            "logs": [],
+            "status": "0x1",
+            "root": null,
+            "logs_bloom": bloom,
+            "effective_gas_price": "0xa",
+        });
+
+        let receipt: StoreTransactionReceipt = serde_json::from_value(json).unwrap();
+        assert_eq!(H256::from_low_u64_be(1), receipt.transaction_hash);
+        assert_eq!(Some(H256::from_low_u64_be(3)), receipt.block_hash);
+        assert_eq!(Some(U64::from(4)), receipt.block_number);
+        assert_eq!(Some(U256::from(21_000)), receipt.gas_used);
+        assert_eq!(Some(U256::from(10)), receipt.effective_gas_price);
+
+        let json = serde_json::to_value(&receipt).unwrap();
+        for field in ["transactionHash", "blockHash", "gasUsed", "logsBloom"] {
+            assert!(json.get(field).is_some(), "missing `{}`", field);
+        }
+        assert!(json.get("gas_used").is_none());
+    }
+
+    #[test]
+    fn receipts_many_from_json() {
+        let receipts = vec![(*receipt(1, 1)).clone(), (*receipt(2, 0)).clone()];
+        let array = serde_json::to_value(&receipts).unwrap();
+
+        assert_eq!(
+            receipts,
+            StoreTransactionReceipt::many_from_json(&array).unwrap()
+        );
+
+        let wrapped = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": array });
+        assert_eq!(
+            receipts,
+            StoreTransactionReceipt::many_from_json(&wrapped).unwrap()
+        );
+
+        let empty = serde_json::json!({ "result": [] });
+        assert!(StoreTransactionReceipt::many_from_json(&empty)
+            .unwrap()
+            .is_empty());
+        assert!(
+            StoreTransactionReceipt::many_from_json(&serde_json::json!([]))
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(StoreTransactionReceipt::many_from_json(&serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn checked_fee_paid() {
+        let receipt = StoreTransactionReceipt {
+            gas_used: Some(U256::MAX),
+            effective_gas_price: Some(U256::from(2)),
+            ..Default::default()
+        };
+        assert_eq!(None, receipt.checked_fee_paid());
+        assert_eq!(None, receipt.fee_paid());
+
+        let block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![Arc::new(receipt.clone()), Arc::new(receipt)],
+        };
+        assert_eq!(None, block.total_fees_paid());
+        assert_eq!(U256::MAX, block.total_gas_used());
+
+        let receipt = StoreTransactionReceipt {
+            gas_used: Some(U256::from(21_000)),
+            effective_gas_price: Some(U256::from(2)),
+            ..Default::default()
+        };
+        assert_eq!(Some(U256::from(42_000)), receipt.checked_fee_paid());
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn ethereum_block_schema() {
+        let schema = serde_json::to_value(super::ethereum_block_schema()).unwrap();
+        let definitions = &schema["definitions"];
+
+        let receipt = &definitions["StoreTransactionReceipt"]["properties"];
+        assert!(receipt.get("logsBloom").is_some());
+        assert!(receipt.get("logs_bloom").is_none());
+        assert!(definitions["LightTransaction"]["properties"]
+            .get("maxFeePerGas")
+            .is_some());
+    }
+
+    #[test]
+    fn receipt_from_ref() {
+        let receipt = TransactionReceipt {
+            gas_used: Some(U256::from(21_000)),
+            status: Some(U64::from(1)),
+            logs: vec![log(1, &[2])],
+            ..web3_receipt()
+        };
+
+        assert_eq!(
+            StoreTransactionReceipt::from(receipt.clone()),
+            StoreTransactionReceipt::from(&receipt)
+        );
+    }
+
+    #[test]
+    fn receipt_type_from_v1() {
+        let receipt = TransactionReceipt {
+            transaction_hash: H256::from_low_u64_be(1),
+            transaction_type: Some(U64::from(3)),
+            ..web3_receipt()
+        };
+        let block = EthereumBlockV2::from(EthereumBlockV1 {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![Arc::new(receipt), Arc::new(web3_receipt())],
+        });
+
+        let blob = &block.transaction_receipts[0];
+        assert_eq!(Some(U64::from(3)), blob.transaction_type);
+        assert!(blob.is_type(3));
+        assert!(!blob.is_type(2));
+
+        let legacy = &block.transaction_receipts[1];
+        assert!(legacy.is_type(0));
+        assert!(!legacy.is_type(3));
+    }
+
+    #[test]
+    fn compact_debug() {
+        let tx = LightTransaction {
+            hash: H256::from_slice(&[0x12; 32]),
+            value: U256::from(10),
+            input: Bytes(vec![0xa9, 0x05, 0x9c, 0xbb, 0, 0]),
+            ..Default::default()
+        };
+        assert_eq!(
+            "LightTransaction { hash: 0x1212…1212, selector: 0xa9059cbb, value: 10, input_len: 6 }",
+            format!("{:?}", tx.compact())
+        );
+
+        let transfer = LightTransaction::default();
+        assert_eq!(
+            "LightTransaction { hash: 0x0000…0000, selector: none, value: 0, input_len: 0 }",
+            format!("{:?}", transfer.compact())
+        );
+    }
+
+    #[test]
+    fn tx_by_hash() {
+        let tx = LightTransaction {
+            hash: H256::from_low_u64_be(1),
+            transaction_index: Some(U64::from(0)),
+            value: U256::from(10),
+            ..Default::default()
+        };
+        let reorged = LightTransaction {
+            transaction_index: Some(U64::from(4)),
+            value: U256::from(20),
+            ..tx.clone()
+        };
+        let other = LightTransaction {
+            hash: H256::from_low_u64_be(2),
+            ..tx.clone()
+        };
+
+        let mut txs = HashSet::new();
+        assert!(txs.insert(TxByHash(tx)));
+        assert!(!txs.insert(TxByHash(reorged)));
+        assert_eq!(1, txs.len());
+        assert!(txs.insert(TxByHash(other)));
+        assert_eq!(2, txs.len());
+    }
+
+    #[test]
+    fn calldata_size_and_cost() {
+        let tx = LightTransaction {
+            input: Bytes(vec![0xa9, 0x05, 0x9c, 0xbb, 0, 0, 0, 0x01]),
+            ..Default::default()
+        };
+        assert_eq!(8, tx.input_size());
+        assert_eq!(5 * 16 + 3 * 4, tx.calldata_gas_cost());
+
+        let block = EthereumBlockBuilder::new()
+            .add_transaction(tx)
+            .add_transaction(LightTransaction {
+                input: Bytes(vec![0; 10]),
+                ..Default::default()
+            })
+            .build();
+        assert_eq!(18, block.total_calldata_bytes());
+    }
+
+    #[test]
+    fn priority_fee_paid() {
+        let legacy = LightTransaction {
+            gas_price: Some(U256::from(30)),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(U256::from(20)),
+            legacy.priority_fee_paid(U256::from(10))
+        );
+        assert_eq!(Some(U256::zero()), legacy.priority_fee_paid(U256::from(40)));
+
+        let dynamic = LightTransaction {
+            transaction_type: Some(U64::from(2)),
+            max_fee_per_gas: Some(U256::from(100)),
+            max_priority_fee_per_gas: Some(U256::from(5)),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(U256::from(5)),
+            dynamic.priority_fee_paid(U256::from(50))
+        );
+        assert_eq!(
+            Some(U256::from(3)),
+            dynamic.priority_fee_paid(U256::from(97))
+        );
+        assert_eq!(
+            Some(U256::zero()),
+            dynamic.priority_fee_paid(U256::from(120))
+        );
+
+        assert_eq!(
+            None,
+            LightTransaction::default().priority_fee_paid(U256::from(10))
+        );
+    }
+
+    #[test]
+    fn to_call_request() {
+        let legacy = LightTransaction {
+            from: Some(Address::from_low_u64_be(1)),
+            to: Some(Address::from_low_u64_be(2)),
+            gas: U256::from(21_000),
+            gas_price: Some(U256::from(5)),
+            value: U256::from(7),
+            input: Bytes(vec![1, 2, 3]),
+            ..Default::default()
+        };
+        let req = legacy.to_call_request();
+        assert_eq!(legacy.from, req.from);
+        assert_eq!(legacy.to, req.to);
+        assert_eq!(Some(legacy.gas), req.gas);
+        assert_eq!(Some(U256::from(5)), req.gas_price);
+        assert_eq!(Some(legacy.value), req.value);
+        assert_eq!(Some(legacy.input.clone()), req.data);
+        assert_eq!(None, req.max_fee_per_gas);
+        assert_eq!(None, req.max_priority_fee_per_gas);
+
+        let dynamic = LightTransaction {
+            transaction_type: Some(U64::from(2)),
+            max_fee_per_gas: Some(U256::from(100)),
+            max_priority_fee_per_gas: Some(U256::from(2)),
+            to: None,
+            ..legacy
+        };
+        let req = dynamic.to_call_request();
+        assert_eq!(None, req.to);
+        assert_eq!(None, req.gas_price);
+        assert_eq!(Some(U64::from(2)), req.transaction_type);
+        assert_eq!(Some(U256::from(100)), req.max_fee_per_gas);
+        assert_eq!(Some(U256::from(2)), req.max_priority_fee_per_gas);
+    }
+
+    #[test]
+    fn recover_sender() {
+        // The example transaction from EIP-155, signed with the private key
+        // 0x4646...46
+        let tx = LightTransaction {
+            nonce: U256::from(9),
+            gas_price: Some(U256::from(20_000_000_000u64)),
+            gas: U256::from(21_000),
+            to: Some(Address::from([0x35; 20])),
+            value: U256::from(1_000_000_000_000_000_000u64),
+            v: Some(U64::from(37)),
+            r: Some(
+                U256::from_dec_str(
+                    "18515461264373351373200002665853028612451056578545711640558177340181847433846",
+                )
+                .unwrap(),
+            ),
+            s: Some(
+                U256::from_dec_str(
+                    "46948507304638947509940763649030358759909902576025900602547168820602576006531",
+                )
+                .unwrap(),
+            ),
+            ..Default::default()
+        };
+        let sender: Address = "9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".parse().unwrap();
+        assert_eq!(Some(sender), tx.recover_sender(None));
+        assert_eq!(Some(sender), tx.recover_sender(Some(1)));
+        assert_eq!(None, tx.recover_sender(Some(5)));
+
+        let known = Address::from_low_u64_be(1);
+        let with_from = LightTransaction {
+            from: Some(known),
+            ..tx.clone()
+        };
+        assert_eq!(Some(known), with_from.recover_sender(None));
+
+        let unsigned = LightTransaction {
+            v: None,
+            ..tx.clone()
+        };
+        assert_eq!(None, unsigned.recover_sender(None));
+
+        let typed = LightTransaction {
+            transaction_type: Some(U64::from(2)),
+            v: Some(U64::from(0)),
+            ..tx.clone()
+        };
+        assert_eq!(None, typed.recover_sender(Some(1)));
+
+        // The same transaction signed by the same key without replay
+        // protection
+        let pre_eip155 = LightTransaction {
+            v: Some(U64::from(28)),
+            r: Some(
+                U256::from_dec_str(
+                    "84725416084526465931747577440828168274047143166256556896014364578875712602637",
+                )
+                .unwrap(),
+            ),
+            s: Some(
+                U256::from_dec_str(
+                    "35525888050413494073824667938317674823173357521041956991286932789484634956667",
+                )
+                .unwrap(),
+            ),
+            ..tx
+        };
+        assert_eq!(Some(sender), pre_eip155.recover_sender(None));
+        assert_eq!(Some(sender), pre_eip155.recover_sender(Some(1)));
+    }
+
+    #[test]
+    fn receipt_fee_paid() {
+        let receipt = StoreTransactionReceipt::from(TransactionReceipt {
+            gas_used: Some(U256::from(21_000)),
+            effective_gas_price: Some(U256::from(10)),
+            transaction_type: Some(U64::from(2)),
+            ..web3_receipt()
+        });
+        assert_eq!(Some(U64::from(2)), receipt.transaction_type);
+        assert_eq!(Some(U256::from(210_000)), receipt.fee_paid());
+
+        let light = StoreTransactionReceipt {
+            gas_used: None,
+            effective_gas_price: Some(U256::from(10)),
+            ..Default::default()
+        };
+        assert_eq!(None, light.fee_paid());
+    }
+
+    #[test]
+    fn blob_fee_paid() {
+        let json = serde_json::json!({
+            "transactionHash": H256::from_low_u64_be(1),
+            "transactionIndex": "0x0",
+            "blockHash": null,
+            "blockNumber": null,
+            "cumulativeGasUsed": "0x5208",
+            "gasUsed": "0x5208",
+            "contractAddress": null,
+            "logs": [],
+            "status": "0x1",
+            "type": "0x3",
+            "blobGasUsed": "0x20000",
+            "blobGasPrice": "0x3",
+        });
+        let blob: StoreTransactionReceipt = serde_json::from_value(json).unwrap();
+        assert!(blob.is_type(3));
+        assert_eq!(Some(U256::from(0x60000)), blob.blob_fee_paid());
+
+        let round_trip = serde_json::to_value(&blob).unwrap();
+        assert_eq!("0x20000", round_trip["blobGasUsed"]);
+
+        let legacy = StoreTransactionReceipt::from(web3_receipt());
+        assert_eq!(None, legacy.blob_fee_paid());
+        assert!(serde_json::to_value(&legacy)
+            .unwrap()
+            .get("blobGasUsed")
+            .is_none());
+    }
+
+    #[test]
+    fn receipt_without_fee_fields_deserializes() {
+        // Receipts that were cached before these fields existed serialize
+        // the same as before
+        let mut json = serde_json::to_value(StoreTransactionReceipt::default()).unwrap();
+        assert!(json.get("effectiveGasPrice").is_none());
+        assert!(json.get("type").is_none());
+
+        let receipt: StoreTransactionReceipt = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(None, receipt.effective_gas_price);
+        assert_eq!(None, receipt.transaction_type);
+
+        let fields = json.as_object_mut().unwrap();
+        fields.insert("effective_gas_price".to_string(), "0x10".into());
+        fields.insert("transaction_type".to_string(), "0x2".into());
+        let receipt: StoreTransactionReceipt = serde_json::from_value(json).unwrap();
+        assert_eq!(Some(U256::from(16)), receipt.effective_gas_price);
+        assert!(receipt.is_type(2));
+    }
+
+    #[test]
+    fn bloom_membership() {
+        let address = Address::from_low_u64_be(0x1234);
+        let topic = H256::from_low_u64_be(0x5678);
+
+        let mut bloom = H2048::zero();
+        for input in [address.as_bytes(), topic.as_bytes()] {
+            for (byte, mask) in bloom_bits(input) {
+                bloom.0[byte] |= mask;
+            }
+        }
+        let block = LightEthereumBlock {
+            logs_bloom: Some(bloom),
+            ..Default::default()
+        };
+        assert!(block.bloom_might_contain_address(&address));
+        assert!(block.bloom_might_contain_topic(&topic));
+
+        let empty = LightEthereumBlock {
+            logs_bloom: Some(H2048::zero()),
+            ..Default::default()
+        };
+        assert!(!empty.bloom_might_contain_address(&address));
+        assert!(!empty.bloom_might_contain_topic(&topic));
+
+        // Without a bloom, we can not rule anything out
+        assert!(LightEthereumBlock::default().bloom_might_contain_address(&address));
+    }
+
+    #[test]
+    fn block_builder() {
+        let block = EthereumBlockBuilder::new().number(1).build();
+        assert_eq!(1, block.block.number());
+        assert!(block.block.try_block_ptr().is_some());
+        assert!(block.transaction_receipts.is_empty());
+
+        let tx = LightTransaction {
+            hash: H256::from_low_u64_be(1),
+            ..Default::default()
+        };
+        let block = EthereumBlockBuilder::new()
+            .number(5)
+            .hash(H256::from_low_u64_be(5))
+            .parent_hash(H256::from_low_u64_be(4))
+            .timestamp(1_000)
+            .add_transaction(tx.clone())
+            .add_receipt((*receipt(1, 1)).clone())
+            .build();
+        assert_eq!(
+            BlockPtr::from((H256::from_low_u64_be(5), 5u64)),
+            block.block.block_ptr()
+        );
+        assert_eq!(
+            Some(BlockPtr::from((H256::from_low_u64_be(4), 4u64))),
+            block.block.parent_ptr()
+        );
+        assert_eq!(1_000, block.block.timestamp().as_secs_since_epoch());
+        assert_eq!(vec![tx], block.block.transactions);
+        assert_eq!(vec![receipt(1, 1)], block.transaction_receipts);
+    }
+
+    #[test]
+    fn conversion_error_display() {
+        let err = ConversionError::new("number", "block is pending");
+        assert_eq!(
+            "failed to convert block field `number`: block is pending",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn try_from_v1() {
+        fn convert(block: LightEthereumBlockV1) -> Result<LightEthereumBlock, ConversionError> {
+            <LightEthereumBlock as LightEthereumBlockTryFromV1To<_>>::try_from(block)
+        }
+
+        let valid = LightEthereumBlockV1 {
+            hash: Some(H256::from_low_u64_be(1)),
+            number: Some(U64::from(1)),
+            transactions: vec![web3_transaction()],
+            ..Default::default()
+        };
+        let block = convert(valid).unwrap();
+        assert_eq!(1, block.number());
+        assert_eq!(1, block.transactions.len());
+
+        let no_number = LightEthereumBlockV1 {
+            hash: Some(H256::from_low_u64_be(1)),
+            number: None,
+            ..Default::default()
+        };
+        assert_eq!("number", convert(no_number).unwrap_err().field);
+
+        let no_hash = LightEthereumBlockV1 {
+            hash: None,
+            number: Some(U64::from(1)),
+            ..Default::default()
+        };
+        assert_eq!("hash", convert(no_hash).unwrap_err().field);
+    }
+
+    #[test]
+    fn v1_round_trip() {
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(1),
+            nonce: U256::from(3),
+            block_hash: Some(H256::from_low_u64_be(10)),
+            block_number: Some(U64::from(10)),
+            transaction_index: Some(U64::from(0)),
+            from: Some(Address::from_low_u64_be(1)),
+            to: Some(Address::from_low_u64_be(2)),
+            value: U256::from(1_000),
+            gas_price: Some(U256::from(5)),
+            gas: U256::from(21_000),
+            input: Bytes(vec![1, 2, 3, 4]),
+            v: Some(U64::from(27)),
+            r: Some(U256::from(1)),
+            s: Some(U256::from(2)),
+            transaction_type: Some(U64::from(0)),
+            ..web3_transaction()
+        };
+        let v1 = LightEthereumBlockV1 {
+            hash: Some(H256::from_low_u64_be(10)),
+            number: Some(U64::from(10)),
+            parent_hash: H256::from_low_u64_be(9),
+            timestamp: U256::from(1_000),
+            transactions: vec![tx.clone()],
+            ..Default::default()
+        };
+
+        let v2 = LightEthereumBlock::from_v1(v1.clone());
+        let downgraded = v2.clone().to_v1();
+
+        assert_eq!(v1.hash, downgraded.hash);
+        assert_eq!(v1.number, downgraded.number);
+        assert_eq!(v1.parent_hash, downgraded.parent_hash);
+        assert_eq!(v1.timestamp, downgraded.timestamp);
+
+        let back = &downgraded.transactions[0];
+        assert_eq!(tx.hash, back.hash);
+        assert_eq!(tx.nonce, back.nonce);
+        assert_eq!(tx.from, back.from);
+        assert_eq!(tx.to, back.to);
+        assert_eq!(tx.value, back.value);
+        assert_eq!(tx.gas_price, back.gas_price);
+        assert_eq!(tx.input, back.input);
+        assert_eq!(tx.transaction_type, back.transaction_type);
+        assert_eq!(tx.v, back.v);
+        assert_eq!(tx.r, back.r);
+        assert_eq!(tx.s, back.s);
+        // Lossy fields
+        assert_eq!(None, back.block_hash);
+
+        // Upgrading again gives the same V2 block
+        assert_eq!(v2, LightEthereumBlock::from_v1(downgraded));
+    }
+
+    #[test]
+    fn v1_conversion_stats() {
+        let full = |status: u64| {
+            Arc::new(TransactionReceipt {
+                gas_used: Some(U256::from(21_000)),
+                status: Some(U64::from(status)),
+                ..web3_receipt()
+            })
+        };
+        let light = Arc::new(TransactionReceipt {
+            gas_used: None,
+            status: None,
+            ..web3_receipt()
+        });
+        let block = EthereumBlockV1 {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![full(1), light.clone(), full(0), light],
+        };
+
+        let (converted, stats) = EthereumBlockV2::from_v1_with_stats(block.clone());
+        assert_eq!(EthereumBlockV2::from(block), converted);
+        assert_eq!(
+            ConversionStats {
+                total_receipts: 4,
+                receipts_missing_gas_used: 2,
+                failed_txns: 1,
+            },
+            stats
+        );
+    }
+
+    fn receipt_with_gas(
+        hash: u64,
+        gas_used: Option<u64>,
+        price: Option<u64>,
+    ) -> Arc<StoreTransactionReceipt> {
+        Arc::new(StoreTransactionReceipt {
+            transaction_hash: H256::from_low_u64_be(hash),
+            transaction_index: U64::from(hash),
+            gas_used: gas_used.map(U256::from),
+            effective_gas_price: price.map(U256::from),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn total_gas_and_fees() {
+        let block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![
+                receipt_with_gas(0, Some(100), Some(2)),
+                receipt_with_gas(1, None, Some(3)),
+                receipt_with_gas(2, Some(50), Some(4)),
+            ],
+        };
+        assert_eq!(U256::from(150), block.total_gas_used());
+        assert_eq!(Some(U256::from(400)), block.total_fees_paid());
+
+        let block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![
+                receipt_with_gas(0, Some(100), Some(2)),
+                receipt_with_gas(1, Some(100), None),
+            ],
+        };
+        assert_eq!(U256::from(200), block.total_gas_used());
+        assert_eq!(None, block.total_fees_paid());
+    }
+
+    #[test]
+    fn backfill_gas_used() {
+        fn receipt(
+            index: u64,
+            cumulative: u64,
+            gas_used: Option<u64>,
+        ) -> Arc<StoreTransactionReceipt> {
+            Arc::new(StoreTransactionReceipt {
+                transaction_index: U64::from(index),
+                cumulative_gas_used: U256::from(cumulative),
+                gas_used: gas_used.map(U256::from),
+                ..Default::default()
+            })
+        }
+
+        let mut block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![
+                receipt(2, 600, Some(250)),
+                receipt(0, 100, Some(100)),
+                receipt(1, 350, None),
+            ],
+        };
+        block.backfill_gas_used();
+        let gas: Vec<_> = block
+            .transaction_receipts
+            .iter()
+            .map(|receipt| (receipt.transaction_index.as_u64(), receipt.gas_used))
+            .collect();
+        assert_eq!(
+            vec![
+                (0, Some(U256::from(100))),
+                (1, Some(U256::from(250))),
+                (2, Some(U256::from(250)))
+            ],
+            gas
+        );
+
+        // Without the preceding receipt, the gas can not be recovered
+        let mut block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![receipt(0, 100, Some(100)), receipt(2, 600, None)],
+        };
+        block.backfill_gas_used();
+        assert_eq!(None, block.transaction_receipts[1].gas_used);
+    }
+
+    #[test]
+    fn deployment_gas() {
+        let deployment = StoreTransactionReceipt {
+            contract_address: Some(Address::from_low_u64_be(0xc0)),
+            ..(*receipt_with_gas(0, Some(500_000), Some(2))).clone()
+        };
+        let block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![
+                Arc::new(deployment),
+                receipt_with_gas(1, Some(21_000), Some(2)),
+            ],
+        };
+        assert_eq!(U256::from(500_000), block.deployment_gas());
+        assert_eq!(U256::from(521_000), block.total_gas_used());
+
+        let light = StoreTransactionReceipt {
+            contract_address: Some(Address::from_low_u64_be(0xc0)),
+            ..(*receipt_with_gas(0, None, None)).clone()
+        };
+        let block = EthereumBlock {
+            block: Arc::new(LightEthereumBlock::default()),
+            transaction_receipts: vec![Arc::new(light)],
+        };
+        assert_eq!(U256::zero(), block.deployment_gas());
+    }
+
+    #[test]
+    fn contract_creation() {
+        let contract = Address::from_low_u64_be(0xc0);
+        let deploy = LightTransaction {
+            hash: H256::from_low_u64_be(1),
+            to: None,
+            ..Default::default()
+        };
+        let transfer = LightTransaction {
+            hash: H256::from_low_u64_be(2),
+            to: Some(Address::from_low_u64_be(2)),
+            ..Default::default()
+        };
+        assert!(deploy.is_contract_creation());
+        assert!(!transfer.is_contract_creation());
+
+        let block = EthereumBlockBuilder::new()
+            .add_transaction(deploy)
+            .add_transaction(transfer)
+            .add_receipt(StoreTransactionReceipt {
+                transaction_hash: H256::from_low_u64_be(1),
+                contract_address: Some(contract),
+                ..Default::default()
+            })
+            .add_receipt(StoreTransactionReceipt {
+                transaction_hash: H256::from_low_u64_be(2),
+                transaction_index: U64::from(1),
+                ..Default::default()
+            })
+            .build();
+        assert_eq!(
+            Some(contract),
+            block.transaction_receipts[0].created_contract()
+        );
+        assert_eq!(None, block.transaction_receipts[1].created_contract());
+        assert_eq!(vec![contract], block.created_contracts());
+    }
+
+    fn log(address: u64, topics: &[u64]) -> Log {
+        Log {
+            address: Address::from_low_u64_be(address),
+            topics: topics.iter().map(|t| H256::from_low_u64_be(*t)).collect(),
+            data: Bytes(vec![]),
+            block_hash: None,
+            block_number: None,
+            transaction_hash: None,
+            transaction_index: None,
+            log_index: None,
+            transaction_log_index: None,
+            log_type: None,
+            removed: None,
+        }
+    }
+
+    #[test]
+    fn logs_with_index() {
+        let removed = Log {
+            removed: Some(true),
+            log_index: Some(U256::from(11)),
+            ..log(2, &[])
+        };
+        let receipt = StoreTransactionReceipt {
+            logs: vec![
+                Log {
+                    log_index: Some(U256::from(10)),
+                    removed: Some(false),
+                    ..log(1, &[])
+                },
+                removed,
+                log(3, &[]),
+            ],
+            ..Default::default()
+        };
+
+        let indexes: Vec<_> = receipt.logs_with_index().map(|(idx, _)| idx).collect();
+        assert_eq!(vec![U256::from(10), U256::from(11), U256::from(2)], indexes);
+
+        let addresses: Vec<_> = receipt.non_removed_logs().map(|log| log.address).collect();
+        assert_eq!(
+            vec![Address::from_low_u64_be(1), Address::from_low_u64_be(3)],
+            addresses
+        );
+    }
+
+    #[test]
+    fn logs_for_topic0() {
+        let sig = H256::from_low_u64_be(0xe1);
+        let receipt = StoreTransactionReceipt {
+            logs: vec![log(1, &[]), log(1, &[0xe1, 0x01]), log(1, &[0x01, 0xe1])],
+            ..Default::default()
+        };
+        let matches: Vec<_> = receipt.logs_for_topic0(sig).collect();
+        assert_eq!(vec![&log(1, &[0xe1, 0x01])], matches);
+
+        let block = EthereumBlockBuilder::new()
+            .add_receipt(receipt)
+            .add_receipt(StoreTransactionReceipt {
+                logs: vec![log(2, &[0xe1])],
+                ..Default::default()
+            })
+            .build();
+        assert_eq!(
+            vec![&log(1, &[0xe1, 0x01]), &log(2, &[0xe1])],
+            block.logs_for_topic0(sig)
+        );
+        assert!(block.logs_for_topic0(H256::zero()).is_empty());
+    }
+
+    #[test]
+    fn logs_with_data_prefix() {
+        let with_data = |data: Vec<u8>| Log {
+            data: Bytes(data),
+            ..log(1, &[0xe1])
+        };
+        let receipt = StoreTransactionReceipt {
+            logs: vec![
+                with_data(vec![0xaa, 0xbb, 0xcc]),
+                with_data(vec![0xaa, 0xcc, 0xbb]),
+                with_data(vec![0xaa]),
+            ],
+            ..Default::default()
+        };
+
+        let matches: Vec<_> = receipt.logs_with_data_prefix(&[0xaa, 0xbb]).collect();
+        assert_eq!(vec![&with_data(vec![0xaa, 0xbb, 0xcc])], matches);
+        assert_eq!(0, receipt.logs_with_data_prefix(&[0xaa; 4]).count());
+    }
+
+    #[test]
+    fn canonical_json() {
+        let tx = LightTransaction {
+            hash: H256::from_low_u64_be(1),
+            ..Default::default()
+        };
+        let block = EthereumBlockBuilder::new()
+            .number(3)
+            .hash(H256::from_low_u64_be(0xa3))
+            .add_transaction(tx.clone())
+            .add_receipt((*receipt(1, 1)).clone())
+            .build();
+        let same = EthereumBlockBuilder::new()
+            .add_receipt((*receipt(1, 1)).clone())
+            .add_transaction(tx)
+            .hash(H256::from_low_u64_be(0xa3))
+            .number(3)
+            .build();
+
+        let canonical = block.to_canonical_json().unwrap();
+        assert_eq!(canonical, same.to_canonical_json().unwrap());
+        assert!(canonical.starts_with(r#"{"block":{"#));
+        assert!(canonical.contains(r#""logsBloom":"#));
+        assert!(!canonical.contains(char::is_whitespace));
+
+        // Parsing the canonical form and encoding it again is stable
+        let parsed: EthereumBlock = serde_json::from_str(&canonical).unwrap();
+        assert_eq!(block, parsed);
+        assert_eq!(canonical, parsed.to_canonical_json().unwrap());
+    }
+
+    #[test]
+    fn hashes_only_round_trip() {
+        let tx = LightTransaction {
+            hash: H256::from_low_u64_be(1),
+            nonce: U256::from(7),
+            input: Bytes(vec![1, 2, 3, 4]),
+            ..Default::default()
+        };
+        let block = EthereumBlockBuilder::new()
+            .number(3)
+            .hash(H256::from_low_u64_be(0xa3))
+            .add_transaction(tx)
+            .add_receipt((*receipt(1, 1)).clone())
+            .build();
+
+        let value = block
+            .serialize_hashes_only(serde_json::value::Serializer)
+            .unwrap();
+        assert_eq!(
+            serde_json::json!([format!("{:?}", H256::from_low_u64_be(1))]),
+            value["block"]["transactions"]
+        );
+
+        let parsed = EthereumBlock::deserialize_hashes_only(value.clone()).unwrap();
+        assert_eq!(block.block.hash, parsed.block.hash);
+        assert_eq!(block.block.number, parsed.block.number);
+        assert_eq!(block.transaction_receipts, parsed.transaction_receipts);
+        assert_eq!(
+            vec![LightTransaction {
+                hash: H256::from_low_u64_be(1),
+                ..Default::default()
+            }],
+            parsed.block.transactions
+        );
+
+        // The hash-only form survives another round trip unchanged
+        assert_eq!(
+            value,
+            parsed
+                .serialize_hashes_only(serde_json::value::Serializer)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn strip_receipts_and_logs() {
+        let block = EthereumBlockBuilder::new()
+            .number(3)
+            .add_receipt(StoreTransactionReceipt {
+                logs: vec![log(1, &[0xa]), log(2, &[0xb])],
+                ..(*receipt(1, 1)).clone()
+            })
+            .add_receipt((*receipt(2, 1)).clone())
+            .build();
+
+        let stripped = block.clone().strip_logs();
+        assert_eq!(2, stripped.transaction_receipts.len());
+        assert!(stripped
+            .transaction_receipts
+            .iter()
+            .all(|receipt| receipt.logs.is_empty()));
+        let json = serde_json::to_string(&stripped).unwrap();
+        assert_eq!(stripped, serde_json::from_str(&json).unwrap());
+
+        let bare = block.clone().without_receipts();
+        assert!(bare.transaction_receipts.is_empty());
+        assert_eq!(block.block, bare.block);
+        let json = serde_json::to_string(&bare).unwrap();
+        assert_eq!(bare, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn with_receipts() {
+        let block_hash = H256::from_low_u64_be(0xa3);
+        let block = EthereumBlockBuilder::new()
+            .number(3)
+            .hash(block_hash)
+            .build()
+            .block;
+        let receipt_in = |tx: u64, block_hash: Option<H256>| {
+            Arc::new(StoreTransactionReceipt {
+                block_hash,
+                ..(*receipt(tx, 1)).clone()
+            })
+        };
+
+        let merged = EthereumBlock::with_receipts(
+            block.cheap_clone(),
+            vec![
+                receipt_in(1, Some(block_hash)),
+                receipt_in(2, Some(block_hash)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(block, merged.block);
+        assert_eq!(2, merged.transaction_receipts.len());
+
+        let other_hash = H256::from_low_u64_be(0xb3);
+        let err = EthereumBlock::with_receipts(
+            block.cheap_clone(),
+            vec![
+                receipt_in(1, Some(block_hash)),
+                receipt_in(2, Some(other_hash)),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(
+            MergeError::BlockHashMismatch {
+                transaction_hash: H256::from_low_u64_be(2),
+                block_hash,
+                receipt_block_hash: Some(other_hash),
+            },
+            err
+        );
+
+        let pending = Arc::new(LightEthereumBlock::default());
+        assert_eq!(
+            MergeError::PendingBlock,
+            EthereumBlock::with_receipts(pending, vec![]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn distinct_topic0s_and_addresses() {
+        let block = EthereumBlockBuilder::new()
+            .add_receipt(StoreTransactionReceipt {
+                logs: vec![log(1, &[0xa, 0xc]), log(2, &[0xa]), log(3, &[])],
+                ..Default::default()
+            })
+            .add_receipt(StoreTransactionReceipt {
+                logs: vec![log(2, &[0xb]), log(1, &[0xa])],
+                ..Default::default()
+            })
+            .build();
+
+        let topics: HashSet<_> = [0xa, 0xb].into_iter().map(H256::from_low_u64_be).collect();
+        assert_eq!(topics, block.distinct_topic0s());
+
+        let addresses: HashSet<_> = [1, 2, 3]
+            .into_iter()
+            .map(Address::from_low_u64_be)
+            .collect();
+        assert_eq!(addresses, block.distinct_log_addresses());
+    }
+
+    #[test]
+    fn logs_for_address() {
+        let block = EthereumBlockBuilder::new()
+            .add_receipt(StoreTransactionReceipt {
+                logs: vec![log(1, &[0xa]), log(2, &[0xa]), log(1, &[0xb])],
+                ..Default::default()
+            })
+            .add_receipt(StoreTransactionReceipt {
+                logs: vec![log(2, &[0xb]), log(1, &[0xa])],
+                ..Default::default()
+            })
+            .build();
+
+        assert_eq!(
+            vec![&log(1, &[0xa]), &log(1, &[0xb]), &log(1, &[0xa])],
+            block.logs_for_address(Address::from_low_u64_be(1))
+        );
+        assert_eq!(
+            vec![&log(2, &[0xa]), &log(2, &[0xb])],
+            block.logs_for_address(Address::from_low_u64_be(2))
+        );
+        assert_eq!(
+            vec![&log(2, &[0xb])],
+            block.logs_for_address_and_topic0(
+                Address::from_low_u64_be(2),
+                H256::from_low_u64_be(0xb)
+            )
+        );
+        assert!(block
+            .logs_for_address(Address::from_low_u64_be(3))
+            .is_empty());
+    }
+
+    #[test]
+    fn logs_by_transaction() {
+        let block = EthereumBlockBuilder::new()
+            .add_receipt(StoreTransactionReceipt {
+                logs: vec![log(3, &[0xa]), log(3, &[0xb])],
+                ..(*receipt(3, 1)).clone()
+            })
+            .add_receipt((*receipt(2, 1)).clone())
+            .add_receipt(StoreTransactionReceipt {
+                logs: vec![log(1, &[0xc])],
+                ..(*receipt(1, 1)).clone()
+            })
+            .build();
+
+        let groups = block.logs_by_transaction();
+        let expected = vec![
+            (
+                H256::from_low_u64_be(1),
+                vec![&block.transaction_receipts[2].logs[0]],
+            ),
+            (H256::from_low_u64_be(2), vec![]),
+            (
+                H256::from_low_u64_be(3),
+                block.transaction_receipts[0].logs.iter().collect(),
+            ),
+        ];
+        assert_eq!(expected, groups);
+        assert_eq!(vec![&log(3, &[0xa]), &log(3, &[0xb])], groups[2].1);
+
+        assert!(EthereumBlock::default().logs_by_transaction().is_empty());
+    }
+
+    #[test]
+    fn transaction_for_receipt() {
+        let tx = LightTransaction {
+            hash: H256::from_low_u64_be(1),
+            input: Bytes(vec![1, 2, 3, 4]),
+            ..Default::default()
+        };
+        let block = EthereumBlockBuilder::new()
+            .add_transaction(tx.clone())
+            .add_receipt((*receipt(1, 1)).clone())
+            .build();
+
+        assert_eq!(Some(&tx), block.transaction_for_receipt(&receipt(1, 1)));
+        assert_eq!(None, block.transaction_for_receipt(&receipt(2, 1)));
+    }
+
+    #[test]
+    fn content_hash_ignores_receipt_order() {
+        let block = |receipts: Vec<u64>| {
+            let mut builder = EthereumBlockBuilder::new()
+                .number(3)
+                .hash(H256::from_low_u64_be(3));
+            for hash in receipts {
+                builder = builder.add_receipt((*receipt(hash, 1)).clone());
+            }
+            builder.build()
+        };
+
+        let a = block(vec![1, 2, 3]);
+        let b = block(vec![3, 1, 2]);
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        let c = block(vec![1, 2]);
+        assert_ne!(a.content_hash(), c.content_hash());
+
+        let d = EthereumBlockBuilder::new()
+            .number(4)
+            .hash(H256::from_low_u64_be(3))
+            .build();
+        assert_ne!(block(vec![]).content_hash(), d.content_hash());
+    }
+
+    #[test]
+    fn flexible_block_number() {
+        let with_number = |number: serde_json::Value| {
+            let mut json = serde_json::to_value(StoreTransactionReceipt::default()).unwrap();
+            json["blockNumber"] = number;
+            serde_json::from_value::<StoreTransactionReceipt>(json)
+                .unwrap()
+                .block_number
+        };
+
+        assert_eq!(Some(U64::from(16)), with_number(serde_json::json!("0x10")));
+        assert_eq!(Some(U64::from(16)), with_number(serde_json::json!("16")));
+        assert_eq!(Some(U64::from(16)), with_number(serde_json::json!(16)));
+        assert_eq!(None, with_number(serde_json::Value::Null));
+
+        let receipt = StoreTransactionReceipt {
+            block_number: Some(U64::from(16)),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(json["blockNumber"], "0x10");
+    }
+
+    #[test]
+    fn parent_child_relationship() {
+        let genesis = EthereumBlockBuilder::new()
+            .number(0)
+            .hash(H256::from_low_u64_be(0xa0))
+            .build();
+        let child = EthereumBlockBuilder::new()
+            .number(1)
+            .hash(H256::from_low_u64_be(0xa1))
+            .parent_hash(H256::from_low_u64_be(0xa0))
+            .build();
+        let genesis_ptr = genesis.block.block_ptr();
+
+        assert!(child.block.is_child_of(&genesis_ptr));
+        assert!(!genesis.block.is_child_of(&genesis_ptr));
+        assert!(genesis.block.is_same_block_as(&genesis_ptr));
+        assert!(!child.block.is_same_block_as(&genesis_ptr));
+
+        let fork = BlockPtr::from((H256::from_low_u64_be(0xb0), 0u64));
+        assert!(!child.block.is_child_of(&fork));
+
+        let pending = LightEthereumBlock::default();
+        assert!(!pending.is_child_of(&genesis_ptr));
+        assert!(!pending.is_same_block_as(&genesis_ptr));
+    }
+
+    #[test]
+    fn unix_timestamp_and_datetime() {
+        let block = EthereumBlockBuilder::new()
+            .timestamp(1_600_000_000)
+            .build()
+            .block;
+        assert_eq!(1_600_000_000, block.unix_timestamp());
+        assert_eq!(
+            "2020-09-13T12:26:40+00:00",
+            block.datetime().unwrap().to_rfc3339()
+        );
+
+        let far_future = LightEthereumBlock {
+            timestamp: U256::MAX,
+            ..Default::default()
+        };
+        assert_eq!(u64::MAX, far_future.unix_timestamp());
+        assert_eq!(None, far_future.datetime());
+    }
+
+    #[test]
+    fn as_tuple() {
+        let block = EthereumBlockBuilder::new()
+            .number(8)
+            .hash(H256::from_low_u64_be(0xa8))
+            .build()
+            .block;
+        assert_eq!(Some((H256::from_low_u64_be(0xa8), 8)), block.as_tuple());
+        assert_eq!(
+            BlockPtr::from((H256::from_low_u64_be(0xa8), 8u64)),
+            block.block_ptr()
+        );
+
+        let pending = LightEthereumBlock::default();
+        assert_eq!(None, pending.as_tuple());
+        assert_eq!(None, pending.try_block_ptr());
+    }
+
+    #[test]
+    fn conflicts_with() {
+        let block = EthereumBlockBuilder::new()
+            .number(5)
+            .hash(H256::from_low_u64_be(0xa5))
+            .build()
+            .block;
+
+        assert!(!block.conflicts_with(&block.block_ptr()));
+        assert!(block.conflicts_with(&BlockPtr::from((H256::from_low_u64_be(0xb5), 5u64))));
+        assert!(!block.conflicts_with(&BlockPtr::from((H256::from_low_u64_be(0xb6), 6u64))));
+
+        let pending = LightEthereumBlock::default();
+        assert!(!pending.conflicts_with(&BlockPtr::from((H256::from_low_u64_be(0xb5), 0u64))));
+    }
+
+    #[test]
+    fn confirmations() {
+        let head = BlockPtr::from((H256::from_low_u64_be(0xff), 100u64));
+        let block = |number| EthereumBlockBuilder::new().number(number).build().block;
+
+        assert_eq!(Some(0), block(100).confirmations(&head));
+        assert!(block(100).is_final(&head, 0));
+        assert!(!block(100).is_final(&head, 1));
+
+        assert_eq!(Some(90), block(10).confirmations(&head));
+        assert!(block(10).is_final(&head, 64));
+
+        assert_eq!(None, block(101).confirmations(&head));
+        assert!(!block(101).is_final(&head, 0));
+
+        let pending = LightEthereumBlock::default();
+        assert_eq!(None, pending.confirmations(&head));
+    }
+
+    #[test]
+    fn heavier_block() {
+        let block = |hash: u64, total_difficulty: Option<u64>| LightEthereumBlock {
+            number: Some(U64::from(7)),
+            hash: Some(H256::from_low_u64_be(hash)),
+            total_difficulty: total_difficulty.map(U256::from),
+            ..Default::default()
+        };
+
+        let light = block(0xa7, Some(1_000));
+        let heavy = block(0xb7, Some(1_200));
+        assert_eq!(Some(U256::from(1_200)), heavy.total_difficulty());
+        assert_eq!(&heavy, super::heavier_block(&light, &heavy));
+        assert_eq!(&heavy, super::heavier_block(&heavy, &light));
+
+        // Ties are broken by hash, regardless of argument order
+        let tied = block(0x17, Some(1_200));
+        assert_eq!(&tied, super::heavier_block(&heavy, &tied));
+        assert_eq!(&tied, super::heavier_block(&tied, &heavy));
+
+        let unknown = block(0x07, None);
+        assert_eq!(&light, super::heavier_block(&unknown, &light));
+    }
+
+    #[test]
+    fn transactions_root_present() {
+        let empty = LightEthereumBlock::default();
+        assert_eq!(0, empty.transaction_count());
+        assert!(!empty.has_transactions());
+        assert!(empty.expected_transactions_root_present());
+
+        let mut block = EthereumBlockBuilder::new()
+            .add_transaction(LightTransaction::default())
+            .add_transaction(LightTransaction::default())
+            .build()
+            .block;
+        assert_eq!(2, block.transaction_count());
+        assert!(block.has_transactions());
+        assert!(!block.expected_transactions_root_present());
+
+        Arc::make_mut(&mut block).transactions_root = H256::from_low_u64_be(1);
+        assert!(block.expected_transactions_root_present());
+    }
+
+    #[test]
+    fn uncles() {
+        let uncles = vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)];
+        let block = LightEthereumBlock {
+            uncles: uncles.clone(),
+            ..Default::default()
+        };
+        assert!(block.has_uncles());
+        assert_eq!(uncles.as_slice(), block.uncle_hashes());
+
+        let block = LightEthereumBlock::default();
+        assert!(!block.has_uncles());
+        assert!(block.uncle_hashes().is_empty());
+    }
+
+    #[test]
+    fn gas_utilization() {
+        let block = |gas_used: u64, gas_limit: u64| LightEthereumBlock {
+            gas_used: U256::from(gas_used),
+            gas_limit: U256::from(gas_limit),
+            ..Default::default()
+        };
+        assert_eq!(Some(0.5), block(15_000_000, 30_000_000).gas_utilization());
+        assert_eq!(Some(0.0), block(0, 30_000_000).gas_utilization());
+        assert_eq!(None, block(0, 0).gas_utilization());
+        // Malformed data where more gas was used than allowed
+        assert_eq!(Some(1.0), block(40_000_000, 30_000_000).gas_utilization());
+    }
+
+    #[test]
+    fn block_ptr_from_hash_block() {
+        let block = Block::<H256> {
+            hash: Some(H256::from_low_u64_be(0xa2)),
+            number: Some(U64::from(2)),
+            transactions: vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+            ..Default::default()
+        };
+        assert_eq!(
+            BlockPtr::from((H256::from_low_u64_be(0xa2), 2u64)),
+            super::block_ptr_from_hash_block(&block).unwrap()
+        );
+
+        let pending = Block::<H256> {
+            hash: None,
+            number: None,
+            ..block.clone()
+        };
+        assert_eq!(
+            Err(EthereumTypeError::MissingBlockNumber),
+            super::block_ptr_from_hash_block(&pending)
+        );
+
+        let no_hash = Block::<H256> {
+            hash: None,
+            ..block.clone()
+        };
+        assert_eq!(
+            Err(EthereumTypeError::MissingBlockHash),
+            super::block_ptr_from_hash_block(&no_hash)
+        );
+
+        let too_large = Block::<H256> {
+            number: Some(U64::from(u64::MAX)),
+            ..block
+        };
+        assert_eq!(
+            Err(EthereumTypeError::BlockNumberOutOfRange(U64::from(
+                u64::MAX
+            ))),
+            super::block_ptr_from_hash_block(&too_large)
+        );
+    }
+
+    #[test]
+    fn header_from_json() {
+        let block = EthereumBlockBuilder::new()
+            .number(7)
+            .hash(H256::from_low_u64_be(7))
+            .parent_hash(H256::from_low_u64_be(6))
+            .timestamp(1_700_000_000)
+            .add_transaction(LightTransaction::from(web3_transaction()))
+            .build()
+            .block;
+        let json = serde_json::to_value(block.as_ref()).unwrap();
+
+        let full: LightEthereumBlock = serde_json::from_value(json.clone()).unwrap();
+        let header = BlockHeader::from_json(&json).unwrap();
+        assert_eq!(full.hash, header.hash);
+        assert_eq!(full.parent_hash, header.parent_hash);
+        assert_eq!(full.number, header.number);
+        assert_eq!(full.timestamp, header.timestamp);
+    }
+
+    #[test]
+    fn withdrawals_from_json() {
+        let mut json = serde_json::to_value(
+            EthereumBlockBuilder::new()
+                .number(17_034_870)
+                .hash(H256::from_low_u64_be(0xa1))
+                .build()
+                .block
+                .as_ref(),
+        )
+        .unwrap();
+
+        // Blocks before Shanghai have no withdrawals
+        let withdrawals = BlockWithdrawals::from_json(&json).unwrap();
+        assert_eq!(None, withdrawals.withdrawals);
+        assert!(withdrawals.withdrawals().is_empty());
+
+        json["withdrawals"] = serde_json::json!([
+            {
+                "index": "0x1",
+                "validatorIndex": "0x5e0c",
+                "address": "0x0000000000000000000000000000000000000aaa",
+                "amount": "0xe8d4a51000"
+            },
+            {
+                "index": "0x2",
+                "validatorIndex": "0x5e0d",
+                "address": "0x0000000000000000000000000000000000000bbb",
+                "amount": "0x3b9aca00"
+            }
+        ]);
+        let withdrawals = BlockWithdrawals::from_json(&json).unwrap();
+        assert_eq!(Some(H256::from_low_u64_be(0xa1)), withdrawals.hash);
+        assert_eq!(Some(U64::from(17_034_870)), withdrawals.number);
+        assert_eq!(
+            vec![
+                Withdrawal {
+                    index: 1,
+                    validator_index: 0x5e0c,
+                    address: Address::from_low_u64_be(0xaaa),
+                    amount: U256::from(1_000_000_000_000u64),
+                },
+                Withdrawal {
+                    index: 2,
+                    validator_index: 0x5e0d,
+                    address: Address::from_low_u64_be(0xbbb),
+                    amount: U256::from(1_000_000_000u64),
+                },
+            ],
+            withdrawals.withdrawals()
+        );
+
+        // The block itself still parses, but loses the withdrawals
+        let block: LightEthereumBlock = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(17_034_870, block.number());
+        assert_eq!(
+            json["withdrawals"][0],
+            serde_json::to_value(&withdrawals.withdrawals()[0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn total_withdrawn() {
+        let withdrawal = |index: u64, address: u64, amount: u64| Withdrawal {
+            index,
+            validator_index: 100 + index,
+            address: Address::from_low_u64_be(address),
+            amount: U256::from(amount),
+        };
+        let block = BlockWithdrawals {
+            hash: Some(H256::from_low_u64_be(1)),
+            number: Some(U64::from(1)),
+            withdrawals: Some(vec![
+                withdrawal(0, 0xaaa, 1_000),
+                withdrawal(1, 0xbbb, 250),
+                withdrawal(2, 0xaaa, 30),
+            ]),
+        };
+
+        assert_eq!(U256::from(1_280), block.total_withdrawn());
+        assert_eq!(
+            vec![withdrawal(0, 0xaaa, 1_000), withdrawal(2, 0xaaa, 30)],
+            block.withdrawals_to(Address::from_low_u64_be(0xaaa))
+        );
+        assert_eq!(
+            vec![withdrawal(1, 0xbbb, 250)],
+            block.withdrawals_to(Address::from_low_u64_be(0xbbb))
+        );
+        assert!(block
+            .withdrawals_to(Address::from_low_u64_be(0xccc))
+            .is_empty());
+
+        let pre_shanghai = BlockWithdrawals::default();
+        assert_eq!(U256::zero(), pre_shanghai.total_withdrawn());
+        assert!(pre_shanghai
+            .withdrawals_to(Address::from_low_u64_be(0xaaa))
+            .is_empty());
+
+        // Going through the block's JSON gives the same result
+        let mut json = serde_json::to_value(
+            EthereumBlockBuilder::new()
+                .number(1)
+                .hash(H256::from_low_u64_be(1))
+                .build()
+                .block
+                .as_ref(),
+        )
+        .unwrap();
+        json["withdrawals"] = serde_json::to_value(block.withdrawals()).unwrap();
+        let parsed = BlockWithdrawals::from_json(&json).unwrap();
+        assert_eq!(block, parsed);
+        assert_eq!(U256::from(1_280), parsed.total_withdrawn());
+    }
+
+    #[test]
+    fn predicted_next_base_fee() {
+        let block = |gas_used: u64| LightEthereumBlock {
+            base_fee_per_gas: Some(U256::from(1_000_000_000)),
+            gas_used: U256::from(gas_used),
+            gas_limit: U256::from(30_000_000),
+            ..Default::default()
+        };
+
+        // At the target the base fee stays the same
+        assert_eq!(
+            Some(U256::from(1_000_000_000)),
+            block(15_000_000).predicted_next_base_fee()
+        );
+        // A full block raises the base fee by 12.5%
+        assert_eq!(
+            Some(U256::from(1_125_000_000)),
+            block(30_000_000).predicted_next_base_fee()
+        );
+        // An empty block lowers the base fee by 12.5%
+        assert_eq!(
+            Some(U256::from(875_000_000)),
+            block(0).predicted_next_base_fee()
+        );
+
+        let pre_london = LightEthereumBlock {
+            base_fee_per_gas: None,
+            ..block(0)
+        };
+        assert_eq!(None, pre_london.predicted_next_base_fee());
+    }
+
+    fn create_trace() -> Trace {
+        use web3::types::{ActionType, Create, CreateResult};
+
+        Trace {
+            action: Action::Create(Create {
+                from: Address::from_low_u64_be(1),
+                value: U256::from(5),
+                gas: U256::from(1_000_000),
+                init: Bytes(vec![0x60, 0x80, 0x60, 0x40]),
+            }),
+            result: Some(Res::Create(CreateResult {
+                gas_used: U256::from(53_000),
+                code: Bytes(vec![0x60, 0x80]),
+                address: Address::from_low_u64_be(0xc0),
+            })),
+            action_type: ActionType::Create,
+            ..call_trace(vec![])
+        }
+    }
+
+    #[test]
+    fn create_from_trace() {
+        let create = EthereumCreate::try_from_trace(&create_trace()).unwrap();
+        assert_eq!(Address::from_low_u64_be(1), create.from);
+        assert_eq!(U256::from(5), create.value);
+        assert_eq!(Bytes(vec![0x60, 0x80, 0x60, 0x40]), create.init);
+        assert_eq!(U256::from(53_000), create.gas_used);
+        assert_eq!(Address::from_low_u64_be(0xc0), create.address);
+        assert_eq!(Some(H256::from_low_u64_be(1)), create.transaction_hash);
+
+        assert_eq!(
+            None,
+            EthereumCreate::try_from_trace(&call_trace(vec![1, 2, 3, 4]))
+        );
+        assert_eq!(
+            Err(TraceRejection::NotACall),
+            EthereumCall::try_from_trace_verbose(&create_trace())
+        );
+    }
+
+    fn suicide_trace() -> Trace {
+        use web3::types::{ActionType, Suicide};
+
+        Trace {
+            action: Action::Suicide(Suicide {
+                address: Address::from_low_u64_be(0xc0),
+                refund_address: Address::from_low_u64_be(1),
+                balance: U256::from(42),
+            }),
+            result: None,
+            action_type: ActionType::Suicide,
+            ..call_trace(vec![])
+        }
+    }
+
+    #[test]
+    fn self_destruct_from_trace() {
+        let destruct = EthereumSelfDestruct::try_from_trace(&suicide_trace()).unwrap();
+        assert_eq!(Address::from_low_u64_be(0xc0), destruct.address);
+        assert_eq!(Address::from_low_u64_be(1), destruct.refund_address);
+        assert_eq!(U256::from(42), destruct.balance);
+        assert_eq!(Some(H256::from_low_u64_be(1)), destruct.transaction_hash);
+
+        assert_eq!(None, EthereumSelfDestruct::try_from_trace(&create_trace()));
+        assert_eq!(
+            None,
+            EthereumSelfDestruct::try_from_trace(&call_trace(vec![1, 2, 3, 4]))
+        );
+    }
+
+    #[test]
+    fn transfer_from_trace() {
+        let mut trace = call_trace(vec![]);
+        if let Action::Call(call) = &mut trace.action {
+            call.value = U256::from(1_000);
+        }
+        trace.trace_address = vec![0];
+
+        let transfer = EthereumTransfer::try_from_trace(&trace).unwrap();
+        assert_eq!(Address::from_low_u64_be(1), transfer.from);
+        assert_eq!(Address::from_low_u64_be(2), transfer.to);
+        assert_eq!(U256::from(1_000), transfer.value);
+        assert_eq!(Some(H256::from_low_u64_be(1)), transfer.transaction_hash);
+        assert_eq!(vec![0], transfer.trace_address);
+        assert_eq!(None, EthereumCall::try_from_trace(&trace));
+
+        // Calls without value, calls with a selector and failed calls are
+        // not transfers
+        assert_eq!(None, EthereumTransfer::try_from_trace(&call_trace(vec![])));
+        let mut with_selector = trace.clone();
+        if let Action::Call(call) = &mut with_selector.action {
+            call.input = Bytes(vec![1, 2, 3, 4]);
+        }
+        assert_eq!(None, EthereumTransfer::try_from_trace(&with_selector));
+        trace.error = Some("Reverted".to_string());
+        assert_eq!(None, EthereumTransfer::try_from_trace(&trace));
+        assert_eq!(None, EthereumTransfer::try_from_trace(&create_trace()));
+    }
+
+    #[test]
+    fn trace_entry() {
+        let call = call_trace(vec![1, 2, 3, 4]);
+        assert_eq!(
+            Some(TraceEntry::Call(
+                EthereumCall::try_from_trace(&call).unwrap()
+            )),
+            TraceEntry::from_trace(&call)
+        );
+
+        let create = create_trace();
+        assert_eq!(
+            Some(TraceEntry::Create(
+                EthereumCreate::try_from_trace(&create).unwrap()
+            )),
+            TraceEntry::from_trace(&create)
+        );
+
+        let suicide = suicide_trace();
+        assert_eq!(
+            Some(TraceEntry::SelfDestruct(
+                EthereumSelfDestruct::try_from_trace(&suicide).unwrap()
+            )),
+            TraceEntry::from_trace(&suicide)
+        );
+
+        for mut trace in [call, create, suicide] {
+            trace.error = Some("Reverted".to_string());
+            assert_eq!(None, TraceEntry::from_trace(&trace));
+        }
+    }
+
+    #[test]
+    fn geth_call_frames() {
+        let address = |n: u64| Address::from_low_u64_be(n);
+        let frame: GethCallFrame = serde_json::from_value(serde_json::json!({
+            "type": "CALL",
+            "from": address(1),
+            "to": address(2),
+            "value": "0x5",
+            "gas": "0x30000",
+            "gasUsed": "0x10000",
+            "input": "0xa9059cbb01",
+            "output": "0x01",
+            "calls": [
+                {
+                    "type": "STATICCALL",
+                    "from": address(2),
+                    "to": address(3),
+                    "gas": "0x1000",
+                    "gasUsed": "0x100",
+                    "input": "0x70a08231",
+                    "output": "0x"
+                },
+                {
+                    "type": "DELEGATECALL",
+                    "from": address(2),
+                    "to": address(4),
+                    "gas": "0x2000",
+                    "gasUsed": "0x200",
+                    "input": "0x12345678",
+                    "calls": [
+                        {
+                            "type": "CALL",
+                            "from": address(2),
+                            "to": address(5),
+                            "value": "0x0",
+                            "gas": "0x800",
+                            "gasUsed": "0x80",
+                            "input": "0xdeadbeef"
+                        }
+                    ]
+                }
+            ]
+        }))
+        .unwrap();
+        let block = BlockPtr::from((H256::from_low_u64_be(0xb1), 7u64));
+        let tx_hash = H256::from_low_u64_be(0x77);
+
+        let calls = EthereumCall::from_geth_call_frame(&frame, &block, tx_hash);
+        let summary: Vec<_> = calls
+            .iter()
+            .map(|call| {
+                (
+                    call.to,
+                    call.call_type,
+                    call.depth,
+                    call.trace_address.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (address(2), CallType::Call, 0, vec![]),
+                (address(3), CallType::StaticCall, 1, vec![0]),
+                (address(4), CallType::DelegateCall, 1, vec![1]),
+                (address(5), CallType::Call, 2, vec![1, 0]),
+            ],
+            summary
+        );
+
+        let root = &calls[0];
+        assert_eq!(U256::from(5), root.value);
+        assert_eq!(U256::from(0x10000), root.gas_used);
+        assert_eq!(Bytes(vec![0x01]), *root.output);
+        assert_eq!(7, root.block_number);
+        assert_eq!(H256::from_low_u64_be(0xb1), root.block_hash);
+        assert_eq!(Some(tx_hash), root.transaction_hash);
+        assert_eq!(U256::zero(), calls[1].value);
+    }
+
+    #[test]
+    fn trace_batches() {
+        let mut reverted = call_trace(vec![5, 6, 7, 8]);
+        reverted.error = Some("Reverted".to_string());
+        let traces = vec![
+            call_trace(vec![1, 2, 3, 4]),
+            create_trace(),
+            call_trace(vec![1]),
+            suicide_trace(),
+            reverted,
+            call_trace(vec![4, 3, 2, 1]),
+        ];
+
+        let calls = EthereumCall::many_from_traces(&traces);
+        let inputs: Vec<_> = calls.iter().map(|call| call.input.0.clone()).collect();
+        assert_eq!(vec![vec![1, 2, 3, 4], vec![4, 3, 2, 1]], inputs);
+
+        let (calls, creates, self_destructs) = partition_traces(&traces);
+        assert_eq!(EthereumCall::many_from_traces(&traces), calls);
+        assert_eq!(
+            vec![EthereumCreate::try_from_trace(&create_trace()).unwrap()],
+            creates
+        );
+        assert_eq!(
+            vec![EthereumSelfDestruct::try_from_trace(&suicide_trace()).unwrap()],
+            self_destructs
+        );
+    }
+
+    #[test]
+    fn call_tree_from_trace_addresses() {
+        let trace = |address: Vec<usize>| {
+            let mut trace = call_trace(vec![1, 2, 3, 4]);
+            trace.trace_address = address;
+            EthereumCall::try_from_trace(&trace).unwrap()
+        };
+        let calls = vec![
+            trace(vec![]),
+            trace(vec![0]),
+            trace(vec![0, 0]),
+            trace(vec![1]),
+        ];
+
+        assert!(calls[0].is_root_call());
+        assert_eq!(None, calls[0].parent_trace_address());
+        assert!(!calls[2].is_root_call());
+        assert_eq!(2, calls[2].depth);
+
+        let children_of = |parent: &EthereumCall| -> Vec<Vec<usize>> {
+            calls
+                .iter()
+                .filter(|call| call.parent_trace_address().as_ref() == Some(&parent.trace_address))
+                .map(|call| call.trace_address.clone())
+                .collect()
+        };
+        assert_eq!(vec![vec![0], vec![1]], children_of(&calls[0]));
+        assert_eq!(vec![vec![0, 0]], children_of(&calls[1]));
+        assert!(children_of(&calls[2]).is_empty());
+        assert!(children_of(&calls[3]).is_empty());
+    }
+
+    #[test]
+    fn block_ptr_from_owned_call() {
+        let call = EthereumCall {
+            block_hash: H256::from_low_u64_be(0xaa),
+            block_number: 12,
+            ..Default::default()
+        };
+        let expected = BlockPtr::from(&call);
+        assert_eq!(expected, BlockPtr::from(call));
+    }
+
+    #[test]
+    fn trace_block_ptr() {
+        let mut trace = call_trace(vec![1, 2, 3, 4]);
+        trace.block_hash = H256::from_low_u64_be(0xbb);
+        trace.block_number = 42;
+        assert_eq!(
+            Some(BlockPtr::from((H256::from_low_u64_be(0xbb), 42))),
+            TraceBlockPtr::block_ptr(&trace)
+        );
+
+        // The trace's block agrees with that of the call made from it
+        let call = EthereumCall::try_from_trace(&trace).unwrap();
+        assert_eq!(Some(BlockPtr::from(&call)), trace.block_ptr());
+
+        trace.block_number = u64::MAX;
+        assert_eq!(None, trace.block_ptr());
+    }
+
+    #[test]
+    fn same_call_as() {
+        let call = EthereumCall {
+            gas_used: U256::from(100),
+            ..call_in(1)
+        };
+        let other = EthereumCall {
+            gas_used: U256::from(200),
+            ..call_in(1)
+        };
+        assert_ne!(call, other);
+        assert!(call.same_call_as(&other));
+
+        assert!(!call.same_call_as(&call_in(2)));
+    }
+
+    #[test]
+    fn trigger_key_ignores_call_tree_position() {
+        let call = EthereumCall {
+            trace_address: vec![0],
+            depth: 1,
+            ..call_in(1)
+        };
+        let nested = EthereumCall {
+            trace_address: vec![0, 2],
+            depth: 2,
+            call_type: CallType::DelegateCall,
+            ..call_in(1)
+        };
+        assert_ne!(call, nested);
+        assert_eq!(call.trigger_key(), nested.trigger_key());
+        assert_ne!(call.trigger_key(), call_in(2).trigger_key());
+    }
+
+    #[test]
+    fn display_call() {
+        let call = EthereumCall {
+            to: Address::from_low_u64_be(0xab),
+            transaction_index: 3,
+            call_type: CallType::StaticCall,
+            input: Arc::new(Bytes(vec![0x12, 0x34, 0x56, 0x78, 0x9a])),
+            ..call_in(1)
+        };
+        assert_eq!(
+            "STATICCALL to 0x00000000000000000000000000000000000000ab \
+             selector 0x12345678 \
+             (tx 0x0000000000000000000000000000000000000000000000000000000000000001#3)",
+            call.to_string()
+        );
+
+        let bare = EthereumCall {
+            input: Arc::new(Bytes(vec![0x12])),
+            ..Default::default()
+        };
+        assert_eq!(
+            "CALL to 0x0000000000000000000000000000000000000000 (no selector) (no tx)",
+            bare.to_string()
+        );
+    }
+
+    #[test]
+    fn call_arg_data() {
+        let call = |input: Vec<u8>| EthereumCall {
+            input: Arc::new(Bytes(input)),
+            ..Default::default()
+        };
+
+        let no_args = call(vec![1, 2, 3, 4]);
+        assert!(no_args.arg_data().is_empty());
+        assert!(no_args.has_complete_words());
+
+        let one_word = call([vec![1, 2, 3, 4], vec![7; 32]].concat());
+        assert_eq!(&[7; 32], one_word.arg_data());
+        assert!(one_word.has_complete_words());
+
+        let truncated = call([vec![1, 2, 3, 4], vec![7; 20]].concat());
+        assert_eq!(20, truncated.arg_data().len());
+        assert!(!truncated.has_complete_words());
+
+        assert!(call(vec![1, 2]).arg_data().is_empty());
+    }
+
+    #[test]
+    fn validate_chain() {
+        let block = |number: u64, hash: u64, parent: u64| {
+            EthereumBlockBuilder::new()
+                .number(number)
+                .hash(H256::from_low_u64_be(hash))
+                .parent_hash(H256::from_low_u64_be(parent))
+                .build()
+                .block
+        };
+
+        let chain = vec![
+            block(1, 0xa1, 0xa0),
+            block(2, 0xa2, 0xa1),
+            block(3, 0xa3, 0xa2),
+        ];
+        assert_eq!(Ok(()), super::validate_chain(&chain));
+        assert_eq!(Ok(()), super::validate_chain(&[]));
+
+        let skip = vec![block(1, 0xa1, 0xa0), block(3, 0xa3, 0xa1)];
+        assert_eq!(
+            Err(ChainGap {
+                index: 1,
+                kind: ChainGapKind::NumberSkip
+            }),
+            super::validate_chain(&skip)
+        );
+
+        let broken = vec![
+            block(1, 0xa1, 0xa0),
+            block(2, 0xa2, 0xa1),
+            block(3, 0xb3, 0xb2),
+        ];
+        assert_eq!(
+            Err(ChainGap {
+                index: 2,
+                kind: ChainGapKind::ParentHashMismatch
+            }),
+            super::validate_chain(&broken)
+        );
+    }
+
+    #[test]
+    fn block_range_cursor() {
+        let mut cursor = BlockRangeCursor::new(10, 12);
+        for number in 10..=12 {
+            assert!(!cursor.is_done());
+            let block = EthereumBlockBuilder::new().number(number).build().block;
+            cursor.advance(&block).unwrap();
+        }
+        assert_eq!(13, cursor.next);
+        assert!(cursor.is_done());
+
+        let mut cursor = BlockRangeCursor::new(10, 12);
+        assert_eq!(
+            Err(EthereumTypeError::MissingBlockNumber),
+            cursor.advance(&LightEthereumBlock::default())
+        );
+        assert_eq!(10, cursor.next);
+
+        let json = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(cursor, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn confirmed_block() {
+        let block = EthereumBlockBuilder::new()
+            .number(4)
+            .hash(H256::from_low_u64_be(0xa4))
+            .build()
+            .block;
+        let confirmed = ConfirmedBlock::try_new(block.cheap_clone()).unwrap();
+        assert_eq!(4, confirmed.number());
+        assert_eq!(H256::from_low_u64_be(0xa4), confirmed.hash());
+        assert_eq!(block.block_ptr(), confirmed.block_ptr());
+        assert!(Arc::ptr_eq(&block, &confirmed.into_inner()));
+
+        let pending = Arc::new(LightEthereumBlock::default());
+        assert_eq!(None, ConfirmedBlock::try_new(pending));
+
+        let no_hash = Arc::new(LightEthereumBlock {
+            number: Some(U64::from(4)),
+            ..Default::default()
+        });
+        assert_eq!(None, ConfirmedBlock::try_new(no_hash));
+
+        let too_high = Arc::new(LightEthereumBlock {
+            number: Some(U64::MAX),
+            hash: Some(H256::from_low_u64_be(0xa4)),
+            ..Default::default()
+        });
+        assert_eq!(None, ConfirmedBlock::try_new(too_high));
+    }
+
+    #[test]
+    fn ordered_blocks() {
+        let block = |number: Option<u64>, hash: u64| {
+            OrderedBlock(Arc::new(LightEthereumBlock {
+                number: number.map(U64::from),
+                hash: Some(H256::from_low_u64_be(hash)),
+                ..Default::default()
+            }))
+        };
+
+        let mut blocks = vec![
+            block(None, 1),
+            block(Some(2), 1),
+            block(Some(1), 2),
+            block(Some(1), 1),
+        ];
+        blocks.sort();
+
+        let keys: Vec<_> = blocks
+            .iter()
+            .map(|b| (b.0.number.map(|n| n.as_u64()), b.0.hash.unwrap()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(1), H256::from_low_u64_be(1)),
+                (Some(1), H256::from_low_u64_be(2)),
+                (Some(2), H256::from_low_u64_be(1)),
+                (None, H256::from_low_u64_be(1)),
+            ],
+            keys
+        );
+    }
+
+    #[test]
+    fn validate_receipts() {
+        let tx = |hash: u64| LightTransaction {
+            hash: H256::from_low_u64_be(hash),
+            ..Default::default()
+        };
+
+        let valid = EthereumBlockBuilder::new()
+            .add_transaction(tx(1))
+            .add_receipt((*receipt(1, 1)).clone())
+            .build();
+        assert_eq!(Ok(()), valid.validate_receipts());
+
+        let invalid = EthereumBlockBuilder::new()
+            .add_transaction(tx(1))
+            .add_transaction(tx(2))
+            .add_receipt((*receipt(1, 1)).clone())
+            .add_receipt((*receipt(3, 1)).clone())
+            .build();
+        assert_eq!(
+            Err(vec![
+                ReceiptMismatch::OrphanReceipt(H256::from_low_u64_be(3)),
+                ReceiptMismatch::MissingReceipt(H256::from_low_u64_be(2)),
+            ]),
+            invalid.validate_receipts()
+        );
+    }
+
+    #[test]
+    fn receipt_ordering() {
+        let block = |indexes: &[u64]| {
+            let mut builder = EthereumBlockBuilder::new();
+            for index in indexes {
+                builder = builder.add_receipt((*receipt(*index, 1)).clone());
+            }
+            builder.build()
+        };
+        let indexes = |receipts: Vec<Arc<StoreTransactionReceipt>>| -> Vec<u64> {
+            receipts
+                .iter()
+                .map(|r| r.transaction_index.as_u64())
+                .collect()
+        };
+
+        let in_order = block(&[0, 1, 2]);
+        assert!(in_order.receipts_are_ordered());
+        assert_eq!(vec![0, 1, 2], indexes(in_order.sorted_receipts()));
+
+        let out_of_order = block(&[1, 0, 2]);
+        assert!(!out_of_order.receipts_are_ordered());
+        assert_eq!(vec![0, 1, 2], indexes(out_of_order.sorted_receipts()));
+
+        let gap = block(&[0, 2, 3]);
+        assert!(!gap.receipts_are_ordered());
+        assert_eq!(vec![0, 2, 3], indexes(gap.sorted_receipts()));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let tx = LightTransaction {
+            hash: H256::from_low_u64_be(1),
+            transaction_type: Some(U64::from(2)),
+            max_fee_per_gas: Some(U256::from(100)),
+            max_priority_fee_per_gas: Some(U256::from(2)),
+            input: Bytes(vec![1, 2, 3, 4]),
+            ..Default::default()
+        };
+        let mut receipt = (*receipt(1, 1)).clone();
+        receipt.logs = (0..50).map(|i| log(i % 3, &[0xe1, i])).collect();
+        let block = EthereumBlockBuilder::new()
+            .number(10)
+            .hash(H256::from_low_u64_be(10))
+            .add_transaction(tx)
+            .add_receipt(receipt)
+            .build();
+
+        let bytes = block.to_bytes().unwrap();
+        assert_eq!(block, EthereumBlock::from_bytes(&bytes).unwrap());
+        assert!(bytes.len() < serde_json::to_vec(&block).unwrap().len());
+
+        // Pending blocks and fields that are `None` survive, too
+        let pending = EthereumBlock::default();
+        let bytes = pending.to_bytes().unwrap();
+        assert_eq!(pending, EthereumBlock::from_bytes(&bytes).unwrap());
+
+        // Data from an unknown version of the encoding is rejected
+        let mut bytes = block.to_bytes().unwrap();
+        bytes[0] += 1;
+        assert!(EthereumBlock::from_bytes(&bytes).is_err());
+        assert!(EthereumBlock::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn binary_size_on_representative_block() {
+        // A deterministic stand-in for random hashes, addresses and amounts
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = |len: usize| -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    seed = seed
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    (seed >> 56) as u8
+                })
+                .collect()
+        };
+        let selectors = [
+            [0xa9, 0x05, 0x9c, 0xbb],
+            [0x23, 0xb8, 0x72, 0xdd],
+            [0x09, 0x5e, 0xa7, 0xb3],
+        ];
+
+        // 150 token transfers with three logs each, roughly the shape of a
+        // busy mainnet block
+        let block_hash = H256::from_slice(&random(32));
+        let mut builder = EthereumBlockBuilder::new()
+            .number(17_000_000)
+            .hash(block_hash)
+            .parent_hash(H256::from_slice(&random(32)));
+        for i in 0..150u64 {
+            let hash = H256::from_slice(&random(32));
+            let from = Address::from_slice(&random(20));
+            let mut input = selectors[i as usize % selectors.len()].to_vec();
+            for _ in 0..3 {
+                input.extend_from_slice(&[0; 12]);
+                input.extend(random(20));
+            }
+            builder = builder.add_transaction(LightTransaction {
+                hash,
+                nonce: U256::from(i),
+                transaction_index: Some(U64::from(i)),
+                from: Some(from),
+                to: Some(Address::from_slice(&random(20))),
+                value: U256::from_big_endian(&random(8)),
+                gas: U256::from(100_000),
+                input: Bytes(input),
+                transaction_type: Some(U64::from(2)),
+                max_fee_per_gas: Some(U256::from(40_000_000_000u64)),
+                max_priority_fee_per_gas: Some(U256::from(1_000_000_000u64)),
+                ..Default::default()
+            });
+
+            let logs = (0..3u64)
+                .map(|j| {
+                    let mut data = vec![0; 24];
+                    data.extend(random(8));
+                    let mut to = vec![0; 12];
+                    to.extend(random(20));
+                    Log {
+                        address: Address::from_slice(&random(20)),
+                        topics: vec![
+                            H256::from_low_u64_be(0xddf2),
+                            H256::from(from),
+                            H256::from_slice(&to),
+                        ],
+                        data: Bytes(data),
+                        block_hash: Some(block_hash),
+                        block_number: Some(U64::from(17_000_000)),
+                        transaction_hash: Some(hash),
+                        transaction_index: Some(U64::from(i)),
+                        log_index: Some(U256::from(i * 3 + j)),
+                        removed: Some(false),
+                        ..log(0, &[])
+                    }
+                })
+                .collect();
+            builder = builder.add_receipt(StoreTransactionReceipt {
+                transaction_hash: hash,
+                transaction_index: U64::from(i),
+                block_hash: Some(block_hash),
+                block_number: Some(U64::from(17_000_000)),
+                cumulative_gas_used: U256::from(60_000 * (i + 1)),
+                gas_used: Some(U256::from(60_000)),
+                logs,
+                status: Some(U64::from(1)),
+                effective_gas_price: Some(U256::from(30_000_000_000u64)),
+                transaction_type: Some(U64::from(2)),
+                ..Default::default()
+            });
+        }
+        let block = builder.build();
+
+        // This is the JSON that the chain store caches for a block
+        let json = serde_json::to_vec(&block).unwrap();
+        let bytes = block.to_bytes().unwrap();
+        assert_eq!(block, EthereumBlock::from_bytes(&bytes).unwrap());
+        // Most of the JSON is hex strings, which take twice the space of the
+        // raw bytes, and the names of fields
+        assert!(
+            bytes.len() * 2 < json.len(),
+            "binary: {} bytes, json: {} bytes",
+            bytes.len(),
+            json.len()
+        );
+    }
+
+    #[test]
+    fn cloned_calls_share_input() {
+        let call = EthereumCall {
+            input: Arc::new(Bytes(vec![7; 1024])),
+            output: Arc::new(Bytes(vec![9; 32])),
+            ..Default::default()
+        };
+
+        let clones: Vec<_> = (0..5000).map(|_| call.clone()).collect();
+        assert!(clones.iter().all(|c| Arc::ptr_eq(&c.input, &call.input)));
+        assert!(clones.iter().all(|c| Arc::ptr_eq(&c.output, &call.output)));
+        assert_eq!(Arc::strong_count(&call.input), 5001);
     }
 }